@@ -1,14 +1,35 @@
-import { readFile } from 'node:fs/promises'
+import { createReadStream } from 'node:fs'
+import { readFile, rm } from 'node:fs/promises'
+import { tmpdir } from 'node:os'
 import { join } from 'node:path'
 import { fileURLToPath } from 'node:url'
 
 import test from 'ava'
 
-import { Whisper, WhisperFullParams, WhisperSamplingStrategy, decodeAudioAsync } from '../index.js'
+import {
+  SubtitleFormat,
+  Whisper,
+  WhisperAlignmentHeadsPreset,
+  WhisperFullParams,
+  WhisperModelStream,
+  WhisperQuantizationType,
+  WhisperSamplingStrategy,
+  WhisperState,
+  WhisperStream,
+  decodeAudioAsync,
+  detectSpeech,
+  diarizeAsync,
+  formatSubtitles,
+  inspectModel,
+  inspectModelAsync,
+  quantizeModelAsync,
+  splitWords,
+} from '../index.js'
 
 const dirname = join(fileURLToPath(import.meta.url), '..')
 
-const GGLM_LARGE = await readFile(join(dirname, '..', 'scripts', 'ggml-tiny.bin'))
+const MODEL_PATH = join(dirname, '..', 'scripts', 'ggml-tiny.bin')
+const GGLM_LARGE = await readFile(MODEL_PATH)
 const AUDIO = await readFile(join(dirname, 'rolldown.wav'))
 
 test('New Whisper from model', async (t) => {
@@ -18,3 +39,675 @@ test('New Whisper from model', async (t) => {
 
   t.notThrows(() => whisper.full(params, audioBuffer))
 })
+
+test('Run Whisper full on the threadpool', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const output = await whisper.fullAsync(params, audioBuffer)
+  t.is(typeof output, 'string')
+  t.true(output.length > 0)
+})
+
+test('Abort Whisper fullAsync', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const controller = new AbortController()
+
+  const output = whisper.fullAsync(params, audioBuffer, controller.signal)
+  controller.abort()
+  await t.throwsAsync(output)
+})
+
+test('Abort Whisper fullAsync from its callbacks', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const long = new Float32Array(audioBuffer.length * 5)
+  for (let i = 0; i < 5; i++) {
+    long.set(audioBuffer, i * audioBuffer.length)
+  }
+
+  for (const callback of ['onProgress', 'onNewSegment'] as const) {
+    const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+    const controller = new AbortController()
+    params[callback] = () => controller.abort()
+    // the run has started, so it stops at the next abort check instead of being cancelled before the threadpool
+    await t.throwsAsync(whisper.fullAsync(params, long, controller.signal), { message: 'Whisper full was aborted' })
+  }
+})
+
+test('Reject runs while the state or the params are busy', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const long = new Float32Array(audioBuffer.length * 5)
+  for (let i = 0; i < 5; i++) {
+    long.set(audioBuffer, i * audioBuffer.length)
+  }
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const started = new Promise((resolve) => {
+    params.onEncoderBegin = resolve
+  })
+
+  const running = whisper.fullAsync(params, long)
+  await started
+  // the synchronous methods do not wait for the run on the threadpool
+  t.throws(() => whisper.full(new WhisperFullParams(WhisperSamplingStrategy.Greedy), audioBuffer), {
+    message: /busy/,
+  })
+  t.throws(() => whisper.fullLangId, { message: /busy/ })
+  await running
+
+  const states = [whisper.createState(), whisper.createState()]
+  const results = await Promise.allSettled(states.map((state) => whisper.fullWithStateAsync(state, params, audioBuffer)))
+  t.deepEqual(results.map((result) => result.status).sort(), ['fulfilled', 'rejected'])
+})
+
+test('Abort only the run of the signal', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const long = new Float32Array(audioBuffer.length * 5)
+  for (let i = 0; i < 5; i++) {
+    long.set(audioBuffer, i * audioBuffer.length)
+  }
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const started = new Promise((resolve) => {
+    params.onEncoderBegin = resolve
+  })
+  const controller = new AbortController()
+
+  const aborted = whisper.fullAsync(params, long, controller.signal)
+  await started
+  controller.abort()
+  // queuing another run on the same params does not clear the abort of the running one
+  const queued = whisper.fullAsync(params, audioBuffer)
+  await t.throwsAsync(aborted, { message: 'Whisper full was aborted' })
+  await queued.catch(() => {})
+
+  // the aborted signal does not stop the next runs
+  t.true((await whisper.fullAsync(params, audioBuffer)).length > 0)
+})
+
+test('Transcribe into segments', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const result = await whisper.transcribeAsync(params, audioBuffer)
+  t.is(result.language, 'en')
+  t.true(result.segments.length > 0)
+  for (const [index, segment] of result.segments.entries()) {
+    t.is(segment.index, index)
+    t.true(segment.end >= segment.start)
+  }
+})
+
+test('Token level data', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.tokenTimestamps = true
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const { segments } = whisper.transcribe(params, audioBuffer)
+  const tokens = segments.flatMap((segment) => segment.tokens)
+  t.true(tokens.length > 0)
+  for (const token of tokens) {
+    t.true(token.p >= 0 && token.p <= 1)
+    t.is(typeof token.start, 'number')
+  }
+})
+
+test('Merge tokens into words', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.tokenTimestamps = true
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const { segments } = await whisper.transcribeAsync(params, audioBuffer)
+  const words = splitWords(segments)
+  t.true(words.length > 0)
+  t.is(words.map((word) => word.text).join(' '), segments.map((segment) => segment.text).join(' '))
+  for (const word of words) {
+    t.false(word.text.startsWith(' '))
+    t.true(word.end! >= word.start!)
+    t.true(word.minProbability <= word.probability && word.probability <= 1)
+  }
+
+  // "é" split across two tokens, each one on its own is not valid UTF-8
+  const token = segments[0].tokens.find((token) => !token.special)!
+  const split = [
+    { ...token, text: ' caf\uFFFD', bytes: [...Buffer.from(' caf'), 0xc3] },
+    { ...token, text: '\uFFFD', bytes: [0xa9] },
+  ]
+  t.deepEqual(
+    splitWords([{ ...segments[0], tokens: split }]).map((word) => word.text),
+    ['café'],
+  )
+})
+
+test('Run multiple states concurrently', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const states = [whisper.createState(), whisper.createState()]
+
+  const outputs = await Promise.all(
+    states.map((state) =>
+      whisper.fullWithStateAsync(state, new WhisperFullParams(WhisperSamplingStrategy.Greedy), audioBuffer),
+    ),
+  )
+  t.is(outputs[0], outputs[1])
+  for (const state of states) {
+    t.true(state.nSegments > 0)
+    t.is(state.text(), outputs[0])
+  }
+
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+  const view = new Promise<WhisperState>((resolve) => {
+    params.onEncoderBegin = resolve
+  })
+  await whisper.fullWithStateAsync(states[0], params, audioBuffer)
+  // the state passed to the callback only keeps what was known when the encoder began
+  const state = await view
+  t.is(state.fullLangId, Whisper.langId('en'))
+  t.throws(() => state.text())
+  t.throws(() => state.result())
+})
+
+test('Transcribe in parallel on split audio', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const long = new Float32Array(audioBuffer.length * 2)
+  long.set(audioBuffer)
+  long.set(audioBuffer, audioBuffer.length)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+
+  const { segments } = await whisper.transcribeParallelAsync(params, long, 2)
+  t.true(segments.length > 0)
+  segments.forEach((segment, i) => {
+    t.is(segment.index, i)
+    t.true(segment.end >= segment.start)
+    if (i > 0) {
+      t.true(segment.start >= segments[i - 1].start)
+    }
+  })
+  // the segments of the second half are shifted by the length of the first one
+  const halfMs = (audioBuffer.length * 1000) / 16000
+  t.true(segments.at(-1).start >= halfMs - 1000)
+})
+
+test('Stream audio with a sliding window', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.singleSegment = true
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const stream = new WhisperStream(whisper, params, { stepMs: 1000, lengthMs: 3000 })
+
+  const updates = []
+  for (let i = 0; i < audioBuffer.length; i += 4000) {
+    updates.push(...(await stream.push(audioBuffer.subarray(i, i + 4000))))
+  }
+  t.true(stream.pendingMs < 1000)
+  updates.push(...(await stream.flush()))
+  t.is(stream.pendingMs, 0)
+
+  t.true(updates.length > 0)
+  t.true(updates.at(-1).isFinal)
+  t.is(updates.at(-1).end, Math.floor((audioBuffer.length * 1000) / 16000))
+  for (const update of updates) {
+    for (const segment of update.segments) {
+      t.true(segment.start >= update.start)
+    }
+  }
+  t.true(updates.filter((update) => update.isFinal).some((update) => update.start > 0))
+})
+
+test('Transcribe long audio in overlapping chunks', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const long = new Float32Array(audioBuffer.length * 3)
+  for (let i = 0; i < 3; i++) {
+    long.set(audioBuffer, i * audioBuffer.length)
+  }
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+  const progress: number[] = []
+  params.onProgress = (value) => {
+    progress.push(value)
+  }
+  const newSegments: string[] = []
+  params.onNewSegment = (segment) => {
+    newSegments.push(segment.text)
+  }
+
+  const chunkMs = Math.ceil((audioBuffer.length * 1000) / 16000)
+  const { segments } = await whisper.transcribeLongAsync(params, long, { chunkMs, overlapMs: 2000 })
+  await new Promise((resolve) => setImmediate(resolve))
+  t.deepEqual(
+    newSegments,
+    segments.map((segment) => segment.text),
+  )
+  t.true(segments.length > 0)
+  segments.forEach((segment, i) => {
+    t.is(segment.index, i)
+    t.true(segment.end >= segment.start)
+    if (i > 0) {
+      t.true(segment.start >= segments[i - 1].end)
+    }
+  })
+  t.true(segments.at(-1).start > chunkMs)
+  t.deepEqual(
+    progress,
+    [...progress].sort((a, b) => a - b),
+  )
+
+  params.initialPrompt = 'Rolldown, Vite.'
+  const prompted = await whisper.transcribeLongAsync(params, long, { chunkMs, overlapMs: 2000 })
+  t.true(prompted.segments.at(-1).start > chunkMs)
+
+  t.throws(() => whisper.transcribeLong(params, long, { chunkMs: 2000, overlapMs: 1000 }))
+})
+
+test('Report the progress of every run from the start', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const runs: number[][] = []
+  params.onProgress = (value) => {
+    runs.at(-1)!.push(value)
+  }
+
+  for (let i = 0; i < 2; i++) {
+    runs.push([])
+    whisper.transcribe(params, audioBuffer)
+    // the progress is delivered to the JS thread after the synchronous run returns
+    await new Promise((resolve) => setImmediate(resolve))
+  }
+  for (const progress of runs) {
+    t.true(progress.length > 0)
+    t.true(progress[0] < 100)
+  }
+})
+
+test('Skip silence with voice activity detection', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  // 5 seconds of silence before the speech
+  const padded = new Float32Array(5 * 16000 + audioBuffer.length)
+  padded.set(audioBuffer, 5 * 16000)
+
+  t.deepEqual(detectSpeech(new Float32Array(16000)), [])
+  const regions = detectSpeech(padded)
+  t.true(regions.length > 0)
+  t.true(regions[0].start >= 4500)
+
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+  params.vad = { speechPadMs: 300 }
+  t.is(params.vad.speechPadMs, 300)
+  t.is(params.vad.minSilenceMs, 500)
+  const { segments } = whisper.transcribe(params, padded)
+  t.true(segments.length > 0)
+  t.true(segments[0].start >= regions[0].start)
+  t.true(segments.at(-1).end <= regions.at(-1).end)
+
+  t.throws(() => {
+    params.vad = { frameMs: 0 }
+  })
+})
+
+test('Format subtitles', (t) => {
+  const token = (text: string, start: number) => ({
+    id: 0,
+    timestampId: 0,
+    text,
+    bytes: [...Buffer.from(text)],
+    special: text.startsWith('[_'),
+    p: 1,
+    plog: 0,
+    pt: 0,
+    ptsum: 0,
+    start,
+    end: start + 400,
+    vlen: 0,
+  })
+  const segments = [
+    {
+      index: 0,
+      text: 'Hello world',
+      start: 0,
+      end: 1500,
+      noSpeechProb: 0,
+      speakerTurnNext: true,
+      tokens: [token('[_BEG_]', 0), token(' Hello', 0), token(' wor', 600), token('ld', 900)],
+    },
+    {
+      index: 1,
+      text: 'Bye',
+      start: 3_725_250,
+      end: 3_726_000,
+      noSpeechProb: 0,
+      speakerTurnNext: false,
+      tokens: [token(' Bye', 3_725_250)],
+    },
+  ]
+
+  t.is(
+    formatSubtitles(segments, SubtitleFormat.Srt),
+    '1\n00:00:00,000 --> 00:00:01,500\nHello world\n\n2\n01:02:05,250 --> 01:02:06,000\nBye\n\n',
+  )
+  t.is(
+    formatSubtitles(segments, SubtitleFormat.WebVtt, { offsetMs: 100, wordTimestamps: true, speakerLabels: true }),
+    'WEBVTT\n\n00:00:00.100 --> 00:00:01.600\n<v Speaker 1>Hello <00:00:00.700>world\n\n01:02:05.350 --> 01:02:06.100\n<v Speaker 2>Bye\n\n',
+  )
+  t.is(
+    formatSubtitles([{ ...segments[1], text: 'Fish & <chips>' }], SubtitleFormat.WebVtt),
+    'WEBVTT\n\n01:02:05.250 --> 01:02:06.000\nFish &amp; &lt;chips&gt;\n\n',
+  )
+  t.is(
+    formatSubtitles(segments, SubtitleFormat.Lrc, { offsetMs: -100 }),
+    '[by:whisper.cpp]\n[00:00.00]Hello world\n[62:05.15]Bye\n',
+  )
+  const ass = formatSubtitles(segments, SubtitleFormat.Ass, {
+    offsetMs: 100,
+    wordTimestamps: true,
+    speakerLabels: true,
+    assStyles: [{ name: 'Left', alignment: 1 }, { name: 'Right', alignment: 3, bold: true }],
+  })
+  t.true(ass.startsWith('[Script Info]\n'))
+  t.true(ass.includes('\nStyle: Right,Arial,64,&H00FFFFFF,&H00A0A0A0,&H00000000,&H80000000,-1,0,0,0,100,100,0,0,1,3,0,3,40,40,50,1\n'))
+  t.true(
+    ass.endsWith(
+      'Dialogue: 0,0:00:00.10,0:00:01.60,Left,Speaker 1,0,0,0,,{\\k60}Hello {\\k90}world\nDialogue: 0,1:02:05.35,1:02:06.10,Right,Speaker 2,0,0,0,,{\\k75}Bye\n',
+    ),
+  )
+  const escaped = { ...segments[1], text: '{\\i1}Fish\\N', tokens: [token(' {\\i1}Fish\\N', 3_725_250)] }
+  t.true(
+    formatSubtitles([escaped], SubtitleFormat.Ass).endsWith(',Default,,0,0,0,,｛＼i1｝Fish＼N\n'),
+  )
+  t.true(
+    formatSubtitles([escaped], SubtitleFormat.Ass, { wordTimestamps: true }).endsWith(
+      ',Default,,0,0,0,,{\\k75}｛＼i1｝Fish＼N\n',
+    ),
+  )
+})
+
+test('Format results as whisper.cpp JSON', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const result = await whisper.transcribeAsync(params, audioBuffer)
+  const json = JSON.parse(whisper.formatJson(params, result, { model: 'ggml-tiny.bin' }))
+  t.is(json.model.type, 'tiny')
+  t.is(json.model.audio.layer, whisper.modelNAudioLayer)
+  t.deepEqual(json.params, { model: 'ggml-tiny.bin', language: 'en', translate: false })
+  t.is(json.result.language, 'en')
+  t.is(json.transcription.length, result.segments.length)
+  const [segment] = json.transcription
+  t.deepEqual(segment.offsets, { from: result.segments[0].start, to: result.segments[0].end })
+  t.is(segment.text.trim(), result.segments[0].text)
+  t.is(segment.tokens[0].id, result.segments[0].tokens[0].id)
+  t.is(segment.tokens[0].t_dtw, -1)
+  t.false('tokens' in JSON.parse(whisper.formatJson(params, result, { full: false })).transcription[0])
+})
+
+test('Label the speakers of the segments', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.tdrzEnable = true
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const result = await whisper.transcribeAsync(params, audioBuffer)
+  let speaker = 0
+  for (const segment of result.segments) {
+    t.is(segment.speaker, speaker)
+    if (segment.speakerTurnNext) speaker = 1 - speaker
+  }
+
+  const segment = (index: number, text: string, speaker: number | undefined, speakerTurnNext: boolean) => ({
+    index,
+    text,
+    start: index * 1000,
+    end: index * 1000 + 1000,
+    noSpeechProb: 0,
+    speakerTurnNext,
+    speaker,
+    tokens: [],
+  })
+  const segments = [segment(0, 'Hi', undefined, true), segment(1, 'Hello', undefined, false), segment(2, 'Bye', 0, false)]
+  t.is(
+    formatSubtitles(segments, SubtitleFormat.Lrc, { speakerLabels: true }),
+    '[by:whisper.cpp]\n[00:00.00]Speaker 1: Hi\n[00:01.00]Speaker 2: Hello\n[00:02.00]Speaker 1: Bye\n',
+  )
+  const json = JSON.parse(whisper.formatJson(params, { langId: 0, language: 'en', segments }))
+  t.deepEqual(
+    json.transcription.map((segment: { speaker?: string; speaker_turn_next: boolean }) => [segment.speaker, segment.speaker_turn_next]),
+    [
+      [undefined, true],
+      [undefined, false],
+      ['0', false],
+    ],
+  )
+})
+
+test('Diarize segments by clustering the voices', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const { segments } = await whisper.transcribeAsync(params, audioBuffer)
+  const single = await diarizeAsync(audioBuffer, segments, { nSpeakers: 1 })
+  t.true(single.every((segment) => segment.speaker === 0))
+  const labeled = await diarizeAsync(audioBuffer, segments, { nSpeakers: 2 })
+  t.is(labeled.length, segments.length)
+  t.is(labeled[0].speaker, 0)
+  t.true(labeled.every((segment, i) => segment.speaker! <= 1 && segment.text === segments[i].text))
+  const estimated = await diarizeAsync(audioBuffer, segments)
+  t.true(estimated.every((segment) => typeof segment.speaker === 'number'))
+})
+
+test('Detect language', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  const detection = await whisper.detectLanguageAsync(audioBuffer)
+  t.is(detection.language, 'en')
+  t.is(detection.langId, Whisper.langId('en'))
+  t.is(detection.probabilities.en, detection.probability)
+})
+
+test('Tokenize and detokenize', (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const text = ' Rolldown is a JavaScript bundler'
+
+  const tokens = whisper.tokenize(text)
+  t.is(tokens.length, whisper.count(text))
+  t.is(whisper.detokenize(tokens), text)
+  t.is(Array.from(tokens, (token) => whisper.tokenToStr(token)).join(''), text)
+  t.true(whisper.tokenEot < whisper.tokenSot)
+  t.throws(() => whisper.tokenToStr(whisper.nVocab))
+})
+
+test('Prompt the decoder with tokens', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+
+  t.is(params.promptTokens, null)
+  const prompt = whisper.tokenize(' Rolldown is a bundler.')
+  params.promptTokens = prompt
+  t.deepEqual(params.promptTokens, prompt)
+  const { segments } = await whisper.transcribeAsync(params, audioBuffer)
+  t.true(segments.length > 0)
+
+  params.promptTokens = null
+  t.is(params.promptTokens, null)
+  params.promptTokens = new Int32Array()
+  t.is(params.promptTokens, null)
+})
+
+test('Grammar constrained decoding', (t) => {
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+
+  t.notThrows(() => params.setGrammar('root ::= cmd " " num\ncmd ::= "open" | "close"\nnum ::= [0-9]+'))
+  t.throws(() => params.setGrammar('root ::= undefined-rule'))
+  t.throws(() => params.setGrammar('cmd ::= "open"', 'root'))
+  t.notThrows(() => params.setGrammar(null))
+})
+
+test('Token bias and suppression', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  params.language = 'en'
+
+  const words = whisper.tokenizeHotWords(['Rolldown'])
+  t.deepEqual(words, [Array.from(whisper.tokenize('Rolldown')), Array.from(whisper.tokenize(' Rolldown'))])
+  t.is(params.hotWords, null)
+  params.hotWords = { words, bias: 5 }
+  t.deepEqual(params.hotWords, { words, bias: 5 })
+  const { segments } = await whisper.transcribeAsync(params, await decodeAudioAsync(AUDIO))
+  t.true(segments.length > 0)
+  params.hotWords = null
+  t.is(params.hotWords, null)
+
+  const bias = [{ token: whisper.tokenize(' Rolldown')[0], bias: 1 }]
+  params.tokenBias = bias
+  params.suppressTokens = whisper.tokenize(' Rollup')
+  t.deepEqual(params.tokenBias, bias)
+  t.deepEqual(params.suppressTokens, whisper.tokenize(' Rollup'))
+})
+
+test('Temperature fallback and beam search params', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.BeamSearch)
+  params.beamSearch = { beamSize: 2, patience: -1 }
+  params.greedy = { bestOf: 2 }
+  params.temperatureInc = 0.2
+  params.entropyThold = 2.4
+  t.is(params.beamSearch.beamSize, 2)
+  t.is(params.greedy.bestOf, 2)
+  t.throws(() => {
+    params.beamSearch = { beamSize: 0, patience: -1 }
+  })
+
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const { segments } = whisper.transcribe(params, audioBuffer)
+  for (const segment of segments) {
+    t.true([0, 0.2, 0.4, 0.6, 0.8, 1].some((temperature) => Math.abs(temperature - segment.temperature!) < 1e-3))
+  }
+})
+
+test('DTW timestamps with custom alignment heads', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE, {
+    dtwTokenTimestamps: true,
+    dtwAheads: [
+      { textLayer: 2, head: 2 },
+      { textLayer: 3, head: 0 },
+      { textLayer: 3, head: 2 },
+      { textLayer: 3, head: 3 },
+      { textLayer: 3, head: 4 },
+    ],
+    dtwMemSize: 64 * 1024 * 1024,
+  })
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const { segments } = whisper.transcribe(params, audioBuffer)
+  t.true(segments.some((segment) => segment.tokens.some((token) => token.dtwTimestamp != null)))
+
+  t.throws(() => new Whisper(GGLM_LARGE, { dtwTokenTimestamps: true, dtwAheadsPreset: WhisperAlignmentHeadsPreset.Custom }))
+})
+
+test('Load model from path and stream', async (t) => {
+  const fromPath = new Whisper(MODEL_PATH)
+  t.is(fromPath.modelNTextLayer, new Whisper(GGLM_LARGE).modelNTextLayer)
+
+  const stream = new WhisperModelStream(1024 * 1024)
+  const loading = Whisper.fromStream(stream)
+  for await (const chunk of createReadStream(MODEL_PATH)) {
+    await stream.write(chunk)
+  }
+  stream.end()
+  const fromStream = await loading
+  t.is(fromStream.modelNTextLayer, fromPath.modelNTextLayer)
+
+  const destroyed = new WhisperModelStream()
+  const failing = Whisper.fromStream(destroyed)
+  await destroyed.write(GGLM_LARGE.subarray(0, 1024))
+  destroyed.destroy()
+  await t.throwsAsync(failing)
+
+  // above the highWaterMark without a loader reading, the write waits until the stream is destroyed
+  const unread = new WhisperModelStream(16)
+  let written = false
+  const writing = unread.write(GGLM_LARGE.subarray(0, 1024)).then(() => {
+    written = true
+  })
+  await new Promise((resolve) => setImmediate(resolve))
+  t.false(written)
+  unread.destroy()
+  await writing
+  t.true(written)
+})
+
+test('Share the model between instances', async (t) => {
+  const first = Whisper.shared(MODEL_PATH)
+  const count = Whisper.sharedModelCount()
+  const second = Whisper.shared(MODEL_PATH)
+  t.is(Whisper.sharedModelCount(), count)
+  t.is(second.modelNTextLayer, first.modelNTextLayer)
+
+  Whisper.shared(MODEL_PATH, { dtwTokenTimestamps: true })
+  t.is(Whisper.sharedModelCount(), count + 1)
+  t.throws(() => Whisper.shared(join(tmpdir(), 'missing-model.bin')))
+
+  t.throws(() => Whisper.shared(GGLM_LARGE))
+  const fromBuffer = Whisper.shared(GGLM_LARGE, null, 'ggml-tiny')
+  Whisper.shared(GGLM_LARGE, null, 'ggml-tiny')
+  t.is(Whisper.sharedModelCount(), count + 2)
+
+  // every instance runs on its own state
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  const outputs = await Promise.all(
+    [first, second, fromBuffer].map((whisper) =>
+      whisper.fullAsync(new WhisperFullParams(WhisperSamplingStrategy.Greedy), audioBuffer),
+    ),
+  )
+  t.is(outputs[0], outputs[1])
+  t.is(outputs[0], outputs[2])
+})
+
+test('Inspect model without loading it', async (t) => {
+  const whisper = new Whisper(GGLM_LARGE)
+  const info = inspectModel(GGLM_LARGE)
+  t.is(info.modelType, 'tiny')
+  t.is(info.nTextLayer, whisper.modelNTextLayer)
+  t.is(info.nVocab, whisper.modelNVocab)
+  t.is(info.isMultilingual, Boolean(whisper.isMultilingual))
+  t.true(info.nTensors > 0)
+  t.regex(info.sha1, /^[0-9a-f]{40}$/)
+  t.deepEqual(await inspectModelAsync(MODEL_PATH), info)
+
+  t.throws(() => inspectModel(GGLM_LARGE.subarray(0, GGLM_LARGE.length - 1)))
+})
+
+test('Quantize model', async (t) => {
+  const output = join(tmpdir(), `ggml-tiny-q5_0-${process.pid}.bin`)
+  t.teardown(() => rm(output, { force: true }))
+  await quantizeModelAsync(MODEL_PATH, output, WhisperQuantizationType.Q5_0)
+
+  const original = inspectModel(MODEL_PATH)
+  const quantized = inspectModel(output)
+  t.is(quantized.ftype, WhisperQuantizationType.Q5_0)
+  t.is(quantized.nTensors, original.nTensors)
+
+  const whisper = new Whisper(output)
+  const params = new WhisperFullParams(WhisperSamplingStrategy.Greedy)
+  const audioBuffer = await decodeAudioAsync(AUDIO)
+  t.true(whisper.full(params, audioBuffer).length > 0)
+})
@@ -14,7 +14,21 @@ export declare class Whisper {
   static lang(id: number): string | null
   /** Return the short string of the specified language name (e.g. 2 -> "german"), returns nullptr if not found */
   static langFull(id: number): string | null
+  /** Load the model from a buffer or a file path, a path is read by whisper.cpp without an intermediate copy */
   constructor(model: Uint8Array | string, params?: WhisperContextParams | undefined | null)
+  /** Load the model from an open file descriptor, e.g. a pipe or a socket. The descriptor is not closed. */
+  static fromFd(fd: number, params?: WhisperContextParams | undefined | null): Whisper
+  /** Load the model from the chunks written to `stream` on the libuv threadpool */
+  static fromStream(stream: WhisperModelStream, params?: WhisperContextParams | undefined | null): Promise<Whisper>
+  /**
+   * Same as the constructor, but reuses the context of a live `Whisper` loaded from the same file with the same params,
+   * in this thread or any `worker_threads`. The weights are freed when the last instance sharing them is garbage collected.
+   * `key` names the model instead of its path and is required for a buffer, instances with the same key share the model.
+   * Every shared instance runs on its own state, so they transcribe concurrently and only share the weights.
+   */
+  static shared(model: Uint8Array | string, params?: WhisperContextParams | undefined | null, key?: string | undefined | null): Whisper
+  /** Number of models currently loaded through `Whisper.shared` in the process */
+  static sharedModelCount(): number
   /** mel length */
   get nLen(): number
   get nVocab(): number
@@ -33,12 +47,82 @@ export declare class Whisper {
   get modelNMels(): number
   get modelFtype(): number
   get modelType(): number
-  /** Language id associated with the context's default state */
+  /** Language id of the last run, on the context's default state or the own state of a shared instance */
   get fullLangId(): number
   get state(): WhisperState | null
-  /** Return the number of tokens in the provided text */
-  count(): number
   full(parmas: WhisperFullParams, samples: Float32Array): string
+  /**
+   * Run the whisper model on the libuv threadpool, the callbacks on `params` keep firing while it runs.
+   * Aborting the `signal` stops the decoding through the abort callback.
+   */
+  fullAsync(params: WhisperFullParams, samples: Float32Array, signal?: AbortSignal | undefined | null): Promise<string>
+  /** Create a new state on the loaded model, each state can run `fullWithState` concurrently */
+  createState(): WhisperState
+  /** Run the whisper model on the provided state instead of the default state of the context */
+  fullWithState(state: WhisperState, params: WhisperFullParams, samples: Float32Array): string
+  /** Async version of `fullWithState`, running on the libuv threadpool */
+  fullWithStateAsync(state: WhisperState, params: WhisperFullParams, samples: Float32Array, signal?: AbortSignal | undefined | null): Promise<string>
+  /** Run the whisper model and return the segments with their timestamps instead of the joined text */
+  transcribe(params: WhisperFullParams, samples: Float32Array): WhisperFullResult
+  /** Async version of `transcribe`, running on the libuv threadpool */
+  transcribeAsync(params: WhisperFullParams, samples: Float32Array, signal?: AbortSignal | undefined | null): Promise<WhisperFullResult>
+  /**
+   * Split the audio into `nProcessors` chunks and run them in parallel with `whisper_full_parallel`.
+   * The segments of all chunks are merged with timestamps relative to the whole audio.
+   * Always runs on the default state of the context, shared instances take turns on it.
+   */
+  transcribeParallel(params: WhisperFullParams, samples: Float32Array, nProcessors: number): WhisperFullResult
+  /** Async version of `transcribeParallel`, running on the libuv threadpool */
+  transcribeParallelAsync(params: WhisperFullParams, samples: Float32Array, nProcessors: number, signal?: AbortSignal | undefined | null): Promise<WhisperFullResult>
+  /**
+   * Transcribe audio of any length in overlapping chunks, each one prompted with the text before it.
+   * The segments of the overlaps are deduplicated and the timestamps refer to the whole audio,
+   * `onProgress` reports the progress of the whole audio and `onNewSegment` fires for the deduplicated segments once their chunk is done.
+   * The language of the result is the one of the first chunk.
+   */
+  transcribeLong(params: WhisperFullParams, samples: Float32Array, options?: WhisperLongOptions | undefined | null): WhisperFullResult
+  /** Async version of `transcribeLong`, running on the libuv threadpool */
+  transcribeLongAsync(params: WhisperFullParams, samples: Float32Array, options?: WhisperLongOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<WhisperFullResult>
+  /**
+   * Serialize a result of this model like the `-ojf` output of the whisper.cpp CLI.
+   * `params` are the params the result was transcribed with.
+   */
+  formatJson(params: WhisperFullParams, result: WhisperFullResult, options?: WhisperJsonOptions | undefined | null): string
+  /** Write the output of `formatJson` to `path` */
+  writeJson(path: string, params: WhisperFullParams, result: WhisperFullResult, options?: WhisperJsonOptions | undefined | null): void
+  /** Detect the spoken language of the first 30 seconds after `offsetMs`, without running the decoder */
+  detectLanguage(samples: Float32Array, options?: DetectLanguageOptions | undefined | null): LanguageDetection
+  /** Async version of `detectLanguage`, running on the libuv threadpool */
+  detectLanguageAsync(samples: Float32Array, options?: DetectLanguageOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<LanguageDetection>
+  /** Return the number of tokens in the provided text */
+  count(text: string): number
+  /** Convert the provided text into tokens */
+  tokenize(text: string): Int32Array
+  /** Return the text of the token, which can be a partial UTF-8 character */
+  tokenToStr(token: number): string
+  /** Convert the tokens back into text */
+  detokenize(tokens: Int32Array): string
+  /**
+   * Tokenize words for `WhisperFullParams.hotWords`, e.g. product names to boost.
+   * Every word is tokenized with and without a leading space.
+   */
+  tokenizeHotWords(words: Array<string>): Array<Array<number>>
+  /** End of transcript */
+  get tokenEot(): number
+  /** Start of transcript */
+  get tokenSot(): number
+  /** Start of the previous text, followed by the prompt tokens */
+  get tokenPrev(): number
+  /** Start of language model */
+  get tokenSolm(): number
+  /** No timestamps */
+  get tokenNot(): number
+  /** Timestamp begin, the first timestamp token */
+  get tokenBeg(): number
+  get tokenTranslate(): number
+  get tokenTranscribe(): number
+  /** Return the token of the specified language id, e.g. `whisper.tokenLang(Whisper.langId('de'))` */
+  tokenLang(langId: number): number
 }
 
 /** Parameters for the whisper_full() function */
@@ -96,6 +180,9 @@ export declare class WhisperFullParams {
   set detectLanguage(value: boolean)
   get initialPrompt(): string
   set initialPrompt(value: string)
+  /** Tokens to provide to the whisper decoder as the previous text, e.g. the tail of the previous chunk */
+  get promptTokens(): Int32Array | null
+  set promptTokens(value?: Int32Array | undefined | null)
   get onEncoderBegin(): (arg: Segment) => void
   get suppressBlank(): boolean
   set suppressBlank(value: boolean)
@@ -103,10 +190,51 @@ export declare class WhisperFullParams {
   set suppressNonSpeechTokens(value: boolean)
   get temperature(): number
   set temperature(value: number)
+  /** Increase of the temperature when the decoding fails the thresholds below, 0 disables the fallback */
+  get temperatureInc(): number
+  set temperatureInc(value: number)
+  /** Fall back to a higher temperature when the entropy of the tokens is below, i.e. the text repeats itself */
+  get entropyThold(): number
+  set entropyThold(value: number)
+  /** Fall back to a higher temperature when the average log probability of the tokens is below */
+  get logprobThold(): number
+  set logprobThold(value: number)
+  /** Treat the window as silence when the no speech probability is above, and the log probability is below `logprobThold` */
+  get noSpeechThold(): number
+  set noSpeechThold(value: number)
+  /** Parameters of the `Greedy` strategy, returns a copy so assign a new object to change them */
+  get greedy(): WhisperGreedyParams
+  set greedy(value: WhisperGreedyParams)
+  /** Parameters of the `BeamSearch` strategy, returns a copy so assign a new object to change them */
+  get beamSearch(): WhisperBeamSearchParams
+  set beamSearch(value: WhisperBeamSearchParams)
   get maxInitialTs(): number
   set maxInitialTs(value: number)
   get lengthPenalty(): number
   set lengthPenalty(value: number)
+  /**
+   * Constrain the decoding with a GBNF grammar, `startRule` defaults to "root".
+   * Pass `null` to remove the grammar.
+   */
+  setGrammar(grammar?: string | undefined | null, startRule?: string | undefined | null): void
+  /**
+   * Voice activity detection run before the decoding, only the speech regions are transcribed.
+   * The timestamps of the segments are mapped back to the original audio, except for `WhisperState.result`.
+   */
+  get vad(): WhisperVadParams | null
+  set vad(value?: WhisperVadParams | undefined | null)
+  /** Penalty applied to the logits of the tokens that do not match the grammar */
+  get grammarPenalty(): number
+  set grammarPenalty(value: number)
+  /** Biases added to the logits of the tokens at every step, see `hotWords` to boost words */
+  get tokenBias(): Array<TokenBias>
+  set tokenBias(value: Array<TokenBias>)
+  /** Words to boost once they are started, e.g. product names that are misspelled */
+  get hotWords(): HotWords | null
+  set hotWords(value?: HotWords | undefined | null)
+  /** Tokens that are never sampled */
+  get suppressTokens(): Int32Array
+  set suppressTokens(value: Int32Array)
   set onEncoderBegin(callback: (arg: WhisperState) => void)
   get onProgress(): (arg: number) => void
   set onProgress(callback: (arg: number) => void)
@@ -116,11 +244,88 @@ export declare class WhisperFullParams {
   set onAbort(callback: () => void)
 }
 
+/**
+ * Writable end for `Whisper.fromStream`, e.g. to load the model from a Node.js `Readable`:
+ * ```js
+ * const stream = new WhisperModelStream()
+ * const loading = Whisper.fromStream(stream)
+ * for await (const chunk of createReadStream(modelPath)) await stream.write(chunk)
+ * stream.end()
+ * const whisper = await loading
+ * ```
+ */
+export declare class WhisperModelStream {
+  /** `highWaterMark` is the number of buffered bytes above which `write` waits for the loader, 16 MiB by default */
+  constructor(highWaterMark?: number | undefined | null)
+  /** Queue a chunk of the model file, resolves once the buffered bytes are below the `highWaterMark` */
+  write(chunk: Uint8Array): Promise<void>
+  /** Signal the end of the model file */
+  end(): void
+  /** Abort the loading, `Whisper.fromStream` rejects */
+  destroy(): void
+}
+
+/**
+ * Decoding state of a run. The state passed to `onEncoderBegin` is a view with only `fullLangId` and `nLen`,
+ * its results throw because the run is still writing them.
+ */
 export declare class WhisperState {
   /** Language id associated with the provided state */
   get fullLangId(): number
   /** mel length */
   get nLen(): number
+  /** Number of segments produced by the last run on this state */
+  get nSegments(): number
+  /** All segment texts of the last run on this state joined together */
+  text(): string
+  /** Segments of the last run on this state */
+  result(): WhisperFullResult
+}
+
+/**
+ * Live transcription of pushed audio with a sliding window, like the whisper.cpp stream example.
+ * Every step decodes the window on its own state, the window is finalized after `lengthMs` of audio
+ * and the next one starts with the last `keepMs` of it.
+ * The callbacks of the params fire for every step, `onNewSegment` with timestamps since the start of the stream.
+ */
+export declare class WhisperStream {
+  constructor(whisper: Whisper, params: WhisperFullParams, options?: WhisperStreamOptions | undefined | null)
+  /** Queue 16 kHz mono samples, resolves with the updates of the steps decoded on the libuv threadpool */
+  push(samples: Float32Array, signal?: AbortSignal | undefined | null): Promise<Array<WhisperStreamUpdate>>
+  /** Decode the rest of the queued audio and finalize the current window */
+  flush(signal?: AbortSignal | undefined | null): Promise<Array<WhisperStreamUpdate>>
+  /** Milliseconds of audio queued and not decoded yet */
+  get pendingMs(): number
+}
+
+/** Style of the ASS output, colours are in the `&HAABBGGRR` notation of ASS */
+export interface AssStyle {
+  /** "Default" for the first style and "Style2", "Style3"... for the next ones by default */
+  name?: string
+  /** "Arial" by default */
+  fontName?: string
+  /** Font size on the 1920x1080 script resolution, 64 by default */
+  fontSize?: number
+  /** Colour of the text, and of the words already spoken with `wordTimestamps`, "&H00FFFFFF" by default */
+  primaryColour?: string
+  /** Colour of the words not spoken yet with `wordTimestamps`, "&H00A0A0A0" by default */
+  secondaryColour?: string
+  /** "&H00000000" by default */
+  outlineColour?: string
+  /** Colour of the shadow, "&H80000000" by default */
+  backColour?: string
+  bold?: boolean
+  italic?: boolean
+  /** Width of the outline in pixels, 3 by default */
+  outline?: number
+  /** Depth of the shadow in pixels, 0 by default */
+  shadow?: number
+  /** Position on the numpad, 2 (bottom center) by default */
+  alignment?: number
+  /** Left and right margin in pixels, 40 by default */
+  marginH?: number
+  /** Vertical margin in pixels, 50 by default */
+  marginV?: number
 }
 
 export declare enum AVLogLevel {
@@ -139,16 +344,193 @@ export declare function decodeAudio(buf: Uint8Array, filename?: string | undefin
 
 export declare function decodeAudioAsync(buf: Uint8Array, filename?: string | undefined | null, signal?: AbortSignal | undefined | null): Promise<Float32Array>
 
+export interface DetectLanguageOptions {
+  /** Offset of the audio to detect the language from, in milliseconds */
+  offsetMs?: number
+  nThreads?: number
+}
+
+/**
+ * Speech regions of 16 kHz mono samples, e.g. the output of `decodeAudio`.
+ * Set `WhisperFullParams.vad` to transcribe only the speech regions.
+ */
+export declare function detectSpeech(samples: Float32Array, params?: WhisperVadParams | undefined | null): Array<SpeechRegion>
+
+/**
+ * Attach a `speaker` to the segments by clustering the voices of the 16 kHz mono samples they were transcribed from,
+ * e.g. the output of `decodeAudio`. Runs offline on the CPU, the speakers are numbered by first appearance.
+ */
+export declare function diarize(samples: Float32Array, segments: Array<Segment>, options?: WhisperDiarizeOptions | undefined | null): Array<Segment>
+
+/** Async version of `diarize`, running on the libuv threadpool */
+export declare function diarizeAsync(samples: Float32Array, segments: Array<Segment>, options?: WhisperDiarizeOptions | undefined | null, signal?: AbortSignal | undefined | null): Promise<Array<Segment>>
+
+/** Serialize the segments of a transcription, e.g. `WhisperFullResult.segments` */
+export declare function formatSubtitles(segments: Array<Segment>, format: SubtitleFormat, options?: SubtitleOptions | undefined | null): string
+
+export interface HotWords {
+  /** Tokens of every word, e.g. from `Whisper.tokenizeHotWords` */
+  words: Array<Array<number>>
+  /** Added to the logits of the next token of a word once its first tokens are decoded */
+  bias: number
+  /**
+   * Added to the logits of the first token of every word, 0 by default.
+   * The first token is often a common word piece, a large value boosts every word starting with it.
+   */
+  startBias?: number
+}
+
+/**
+ * Read the hyper parameters of a model file or buffer and validate its layout without loading the weights.
+ * The whole file is read once for the checksum.
+ */
+export declare function inspectModel(model: Uint8Array | string): ModelInfo
+
+/** Async version of `inspectModel`, running on the libuv threadpool */
+export declare function inspectModelAsync(model: Uint8Array | string, signal?: AbortSignal | undefined | null): Promise<ModelInfo>
+
+export interface LanguageDetection {
+  /** Id of the most probable language */
+  langId: number
+  /** Short string of the most probable language, e.g. "en" */
+  language: string
+  probability: number
+  /** Probability of every language, keyed by the short string of the language */
+  probabilities: Record<string, number>
+}
+
+/** Hyper parameters and layout of a whisper.cpp model file, see `inspectModel` */
+export interface ModelInfo {
+  nVocab: number
+  nAudioCtx: number
+  nAudioState: number
+  nAudioHead: number
+  nAudioLayer: number
+  nTextCtx: number
+  nTextState: number
+  nTextHead: number
+  nTextLayer: number
+  nMels: number
+  /** Weight type of the model, e.g. 1 for F16, see `ggml_ftype` */
+  ftype: number
+  qntVersion: number
+  /** e.g. "base", "unknown" for shapes not released by OpenAI */
+  modelType: string
+  isMultilingual: boolean
+  nTensors: number
+  /** SHA-1 of the whole file in hex, the checksum listed for the models on huggingface.co/ggerganov/whisper.cpp */
+  sha1: string
+}
+
+/** Quantize an f16 or f32 ggml model file like the whisper.cpp quantize tool, the progress of every tensor is logged to `setupLogger` */
+export declare function quantizeModel(input: string, output: string, quantizationType: WhisperQuantizationType): void
+
+/** Async version of `quantizeModel`, running on the libuv threadpool */
+export declare function quantizeModelAsync(input: string, output: string, quantizationType: WhisperQuantizationType, signal?: AbortSignal | undefined | null): Promise<void>
+
 export interface Segment {
+  /** Index of the segment in the transcription */
+  index: number
   text: string
+  /** Start time in milliseconds */
   start: number
+  /** End time in milliseconds */
   end: number
+  /** Probability that the segment contains no speech */
+  noSpeechProb: number
+  /** The next segment is predicted as a speaker turn, requires `tdrzEnable` */
+  speakerTurnNext: boolean
+  /**
+   * Speaker of the segment starting at 0, alternating at the turns detected with `tdrzEnable` or clustered by `diarize`.
+   * Counted per window by `WhisperStream`, not reported by `WhisperState.result`
+   */
+  speaker?: number
+  /**
+   * Best-effort estimate of the temperature the segment was finally decoded at after the fallbacks,
+   * whisper.cpp does not report it so it is counted from the decoding attempts of the window.
+   * Not reported by `transcribeParallel` and `WhisperState.result`
+   */
+  temperature?: number
+  tokens: Array<Token>
 }
 
 export declare function setupLogger(callback: (arg0: WhisperLogLevel, arg1: string) => void): void
 
+export interface SpeechRegion {
+  /** Start time in milliseconds */
+  start: number
+  /** End time in milliseconds */
+  end: number
+}
+
 export declare function splitAudioFromVideo(filepath: string, logLevel?: AVLogLevel | undefined | null): Float32Array
 
+/**
+ * Merge the tokens of the segments into words for click-to-seek transcripts, e.g. of `WhisperFullResult.segments`.
+ * The timestamps come from DTW when `dtwTokenTimestamps` is enabled on the context, from `tokenTimestamps` otherwise.
+ */
+export declare function splitWords(segments: Array<Segment>): Array<Word>
+
+export declare enum SubtitleFormat {
+  Srt = 0,
+  WebVtt = 1,
+  Lrc = 2,
+  /** Advanced SubStation Alpha */
+  Ass = 3
+}
+
+export interface SubtitleOptions {
+  /** Added to every timestamp in milliseconds, negative values show the subtitles earlier */
+  offsetMs?: number
+  /** Prefix the cues with `Segment.speaker`, alternating at the turns detected with `tdrzEnable` if the segments have none */
+  speakerLabels?: boolean
+  /** Highlight the words of the WebVTT and ASS cues as they are spoken, requires `tokenTimestamps` or `dtwTokenTimestamps` */
+  wordTimestamps?: boolean
+  /** Styles of the ASS output, the speakers take turns using them with `speakerLabels` */
+  assStyles?: Array<AssStyle>
+}
+
+/** Token level data of a segment, see `whisper_token_data` */
+export interface Token {
+  id: number
+  /** Id of the forced timestamp token */
+  timestampId: number
+  /** Text of the token, a multi-byte character split across tokens shows up as U+FFFD in each of them */
+  text: string
+  /** Raw bytes of the text, joining them across tokens decodes the split characters */
+  bytes: Array<number>
+  /** Special tokens like `[_BEG_]` and the timestamps are not part of the text */
+  special: boolean
+  /** Probability of the token */
+  p: number
+  /** Log probability of the token */
+  plog: number
+  /** Probability of the timestamp token */
+  pt: number
+  /** Sum of probabilities of all timestamp tokens */
+  ptsum: number
+  /** Start time in milliseconds, requires `tokenTimestamps` */
+  start?: number
+  /** End time in milliseconds, requires `tokenTimestamps` */
+  end?: number
+  /** DTW timestamp in milliseconds, requires `dtwTokenTimestamps` on the context */
+  dtwTimestamp?: number
+  /** Voice length of the token */
+  vlen: number
+}
+
+export interface TokenBias {
+  token: number
+  /** Added to the logit of the token, positive values make the token more likely */
+  bias: number
+}
+
+/** Cross-attention head used for the DTW token timestamps */
+export interface WhisperAlignmentHead {
+  textLayer: number
+  head: number
+}
+
 export declare enum WhisperAlignmentHeadsPreset {
   None = 0,
   NTopMost = 1,
@@ -168,6 +550,7 @@ export declare enum WhisperAlignmentHeadsPreset {
 
 export interface WhisperBeamSearchParams {
   beamSize: number
+  /** Not implemented in whisper.cpp, kept for compatibility with the reference implementation */
   patience: number
 }
 
@@ -177,14 +560,46 @@ export interface WhisperContextParams {
   gpuDevice?: number
   /** [EXPERIMENTAL] Token-level timestamps with DTW */
   dtwTokenTimestamps?: boolean
+  /** Picked from the model type when omitted, `NTopMost` for models without a preset or an unreadable header */
   dtwAheadsPreset?: WhisperAlignmentHeadsPreset
   dtwNTop?: number
+  /** Alignment heads of the `Custom` preset, e.g. for fine-tuned models */
+  dtwAheads?: Array<WhisperAlignmentHead>
+  /** Memory for the DTW computation in bytes, 128 MiB by default */
+  dtwMemSize?: number
+}
+
+export interface WhisperDiarizeOptions {
+  /** Number of speakers if known, estimated with `threshold` otherwise */
+  nSpeakers?: number
+  /**
+   * Voices further apart than this are different speakers, in standard deviations of the features, 0.5 by default.
+   * Lower values find more speakers
+   */
+  threshold?: number
+}
+
+/** Result of a `whisper_full` run */
+export interface WhisperFullResult {
+  /** Id of the detected (or requested) language */
+  langId: number
+  /** Short string of the language, e.g. "en" */
+  language?: string
+  segments: Array<Segment>
 }
 
 export interface WhisperGreedyParams {
+  /** Number of candidates sampled when the temperature is above 0 */
   bestOf: number
 }
 
+export interface WhisperJsonOptions {
+  /** Written to `params.model`, the model path passed to the whisper.cpp CLI */
+  model?: string
+  /** Include the tokens of the segments like `-ojf`, true by default. `false` matches `-oj` */
+  full?: boolean
+}
+
 export declare enum WhisperLogLevel {
   None = 0,
   Info = 1,
@@ -194,7 +609,82 @@ export declare enum WhisperLogLevel {
   Cont = 5
 }
 
+export interface WhisperLongOptions {
+  /** Length of the chunks in milliseconds, 300000 (5 minutes) by default */
+  chunkMs?: number
+  /** Audio shared by consecutive chunks in milliseconds, 5000 by default */
+  overlapMs?: number
+  /** Use the text before the chunk as prompt, after `promptTokens` or `initialPrompt` of the params, true by default */
+  keepContext?: boolean
+}
+
+/** Target weight type of `quantizeModel`, the values are the `ggml_ftype` accepted by the whisper.cpp quantize tool */
+export declare enum WhisperQuantizationType {
+  Q4_0 = 2,
+  Q4_1 = 3,
+  Q8_0 = 7,
+  Q5_0 = 8,
+  Q5_1 = 9
+}
+
 export declare enum WhisperSamplingStrategy {
   Greedy = 0,
   BeamSearch = 1
 }
+
+export interface WhisperStreamOptions {
+  /** Audio decoded per step in milliseconds, 3000 by default */
+  stepMs?: number
+  /** Length of the sliding window in milliseconds, 10000 by default */
+  lengthMs?: number
+  /** Audio of the finalized window kept for the next one in milliseconds, 200 by default */
+  keepMs?: number
+  /** Use the tokens of the finalized window as prompt of the next one, true by default */
+  keepContext?: boolean
+}
+
+/** Transcription of the sliding window after a step */
+export interface WhisperStreamUpdate {
+  /** A finalized window is not decoded again, a partial one is replaced by the next update */
+  isFinal: boolean
+  /** Start of the window in milliseconds since the start of the stream */
+  start: number
+  /** End of the window in milliseconds since the start of the stream */
+  end: number
+  /** Segments of the window with timestamps since the start of the stream */
+  segments: Array<Segment>
+}
+
+/** Energy based voice activity detection, the noise floor is estimated from the quietest frames of the audio */
+export interface WhisperVadParams {
+  /** Frames louder than the noise floor by this many decibels are speech, 10 by default */
+  thresholdDb?: number
+  /** Frames quieter than this level in dBFS are never speech, -50 by default */
+  minEnergyDb?: number
+  /** Length of the analysis frames, 30 by default */
+  frameMs?: number
+  /** Shorter speech regions are dropped, 250 by default */
+  minSpeechMs?: number
+  /** Shorter pauses do not split a speech region, 500 by default */
+  minSilenceMs?: number
+  /** Audio kept before and after every speech region, 200 by default */
+  speechPadMs?: number
+}
+
+export interface Word {
+  /** Text of the word without the leading space, punctuation stays attached */
+  text: string
+  /** Start time in milliseconds, requires `tokenTimestamps` or `dtwTokenTimestamps` on the context */
+  start?: number
+  /** End time in milliseconds, requires `tokenTimestamps` or `dtwTokenTimestamps` on the context */
+  end?: number
+  /** Mean probability of the tokens of the word */
+  probability: number
+  /** Lowest probability of the tokens of the word */
+  minProbability: number
+  /** Index of the segment the word belongs to */
+  segment: number
+}
+
+/** Write the output of `formatSubtitles` to `path` */
+export declare function writeSubtitles(path: string, segments: Array<Segment>, format: SubtitleFormat, options?: SubtitleOptions | undefined | null): void
@@ -366,12 +366,26 @@ if (!nativeBinding) {
 
 module.exports.Whisper = nativeBinding.Whisper
 module.exports.WhisperFullParams = nativeBinding.WhisperFullParams
+module.exports.WhisperModelStream = nativeBinding.WhisperModelStream
 module.exports.WhisperState = nativeBinding.WhisperState
+module.exports.WhisperStream = nativeBinding.WhisperStream
 module.exports.AVLogLevel = nativeBinding.AVLogLevel
 module.exports.decodeAudio = nativeBinding.decodeAudio
 module.exports.decodeAudioAsync = nativeBinding.decodeAudioAsync
+module.exports.detectSpeech = nativeBinding.detectSpeech
+module.exports.diarize = nativeBinding.diarize
+module.exports.diarizeAsync = nativeBinding.diarizeAsync
+module.exports.formatSubtitles = nativeBinding.formatSubtitles
+module.exports.inspectModel = nativeBinding.inspectModel
+module.exports.inspectModelAsync = nativeBinding.inspectModelAsync
+module.exports.quantizeModel = nativeBinding.quantizeModel
+module.exports.quantizeModelAsync = nativeBinding.quantizeModelAsync
 module.exports.setupLogger = nativeBinding.setupLogger
 module.exports.splitAudioFromVideo = nativeBinding.splitAudioFromVideo
+module.exports.splitWords = nativeBinding.splitWords
+module.exports.SubtitleFormat = nativeBinding.SubtitleFormat
 module.exports.WhisperAlignmentHeadsPreset = nativeBinding.WhisperAlignmentHeadsPreset
 module.exports.WhisperLogLevel = nativeBinding.WhisperLogLevel
+module.exports.WhisperQuantizationType = nativeBinding.WhisperQuantizationType
 module.exports.WhisperSamplingStrategy = nativeBinding.WhisperSamplingStrategy
+module.exports.writeSubtitles = nativeBinding.writeSubtitles
@@ -1,8 +1,12 @@
+use std::{fs::File, io::BufReader};
+
+use napi::{bindgen_prelude::Either, Error, Result, Status};
 use napi_derive::napi;
 
-use crate::sys;
+use crate::{log, model_header::ModelHeader, sys, WhisperLogLevel};
 
 #[napi]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum WhisperAlignmentHeadsPreset {
   None = 0,
   NTopMost = 1,
@@ -21,6 +25,15 @@ pub enum WhisperAlignmentHeadsPreset {
 }
 
 #[napi(object)]
+#[derive(Debug, Clone, Copy)]
+/// Cross-attention head used for the DTW token timestamps
+pub struct WhisperAlignmentHead {
+  pub text_layer: i32,
+  pub head: i32,
+}
+
+#[napi(object)]
+#[derive(Default)]
 pub struct WhisperContextParams {
   pub use_gpu: Option<bool>,
   pub flash_attn: Option<bool>,
@@ -28,26 +41,113 @@ pub struct WhisperContextParams {
   pub gpu_device: Option<u32>,
   /// [EXPERIMENTAL] Token-level timestamps with DTW
   pub dtw_token_timestamps: Option<bool>,
+  /// Picked from the model type when omitted, `NTopMost` for models without a preset or an unreadable header
   pub dtw_aheads_preset: Option<WhisperAlignmentHeadsPreset>,
   pub dtw_n_top: Option<i32>,
+  /// Alignment heads of the `Custom` preset, e.g. for fine-tuned models
+  pub dtw_aheads: Option<Vec<WhisperAlignmentHead>>,
+  /// Memory for the DTW computation in bytes, 128 MiB by default
+  pub dtw_mem_size: Option<i64>,
 }
 
-impl From<WhisperContextParams> for sys::whisper_context_params {
-  fn from(params: WhisperContextParams) -> Self {
-    Self {
-      use_gpu: params.use_gpu.unwrap_or(true),
-      flash_attn: params.flash_attn.unwrap_or(false),
-      gpu_device: params.gpu_device.unwrap_or(0) as i32,
-      dtw_token_timestamps: params.dtw_token_timestamps.unwrap_or(false),
-      dtw_aheads_preset: params
-        .dtw_aheads_preset
-        .unwrap_or(WhisperAlignmentHeadsPreset::None) as u32,
-      dtw_n_top: params.dtw_n_top.unwrap_or(-1),
-      dtw_aheads: sys::whisper_aheads {
-        n_heads: 0,
-        heads: std::ptr::null(),
+/// `whisper_context_params` with the alignment heads it points to,
+/// whisper.cpp reads them whenever a state is created so they must live as long as the context
+pub(crate) struct ContextParams {
+  pub(crate) inner: sys::whisper_context_params,
+  pub(crate) aheads: Vec<sys::whisper_ahead>,
+}
+
+impl ContextParams {
+  /// Resolve the params for a model buffer or file, the header picks the DTW alignment heads preset
+  pub(crate) fn for_model(
+    model: Either<&[u8], &str>,
+    params: Option<WhisperContextParams>,
+  ) -> Result<Self> {
+    Self::new(params, || match model {
+      Either::A(buf) => ModelHeader::read(buf),
+      Either::B(filepath) => ModelHeader::read(BufReader::new(File::open(filepath)?)),
+    })
+  }
+
+  /// `header` is only read when the DTW token timestamps need a preset,
+  /// a header that fails to parse falls back to `NTopMost` and leaves the error to whisper.cpp
+  pub(crate) fn new(
+    params: Option<WhisperContextParams>,
+    header: impl FnOnce() -> Result<ModelHeader>,
+  ) -> Result<Self> {
+    let params = params.unwrap_or_default();
+    let dtw_token_timestamps = params.dtw_token_timestamps.unwrap_or(false);
+    let mut dtw_n_top = params.dtw_n_top.unwrap_or(-1);
+    let dtw_aheads_preset = match params.dtw_aheads_preset {
+      Some(preset) => preset,
+      None if params.dtw_aheads.is_some() => WhisperAlignmentHeadsPreset::Custom,
+      None if dtw_token_timestamps => match header() {
+        Ok(header) => header.alignment_heads_preset().unwrap_or_else(|| {
+          if dtw_n_top < 0 {
+            dtw_n_top = header.dtw_n_top();
+          }
+          WhisperAlignmentHeadsPreset::NTopMost
+        }),
+        Err(err) => {
+          log(
+            WhisperLogLevel::Warn,
+            &format!(
+              "cannot pick the alignment heads from the model header, using the top-most layer: {}",
+              err.reason
+            ),
+          );
+          // the number of text layers is unknown, the last one exists in every model
+          if dtw_n_top < 0 {
+            dtw_n_top = 1;
+          }
+          WhisperAlignmentHeadsPreset::NTopMost
+        }
       },
-      dtw_mem_size: 1024 * 1024 * 128,
+      None => WhisperAlignmentHeadsPreset::None,
+    };
+    let aheads = params
+      .dtw_aheads
+      .unwrap_or_default()
+      .into_iter()
+      .map(|head| sys::whisper_ahead {
+        n_text_layer: head.text_layer,
+        n_head: head.head,
+      })
+      .collect::<Vec<_>>();
+    if dtw_token_timestamps
+      && dtw_aheads_preset == WhisperAlignmentHeadsPreset::Custom
+      && aheads.is_empty()
+    {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "dtwAheads is required by the Custom alignment heads preset",
+      ));
     }
+    let dtw_mem_size = match params.dtw_mem_size {
+      Some(size) if size <= 0 => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "dtwMemSize must be greater than 0",
+        ))
+      }
+      Some(size) => size as usize,
+      None => 1024 * 1024 * 128,
+    };
+    Ok(Self {
+      inner: sys::whisper_context_params {
+        use_gpu: params.use_gpu.unwrap_or(true),
+        flash_attn: params.flash_attn.unwrap_or(false),
+        gpu_device: params.gpu_device.unwrap_or(0) as i32,
+        dtw_token_timestamps,
+        dtw_aheads_preset: dtw_aheads_preset as u32,
+        dtw_n_top,
+        dtw_aheads: sys::whisper_aheads {
+          n_heads: aheads.len(),
+          heads: aheads.as_ptr(),
+        },
+        dtw_mem_size,
+      },
+      aheads,
+    })
   }
 }
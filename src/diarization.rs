@@ -0,0 +1,409 @@
+// Speakers of the segments, from the turns of tinydiarize or by clustering the voices of the segments
+
+use std::f32::consts::PI;
+
+use napi::{bindgen_prelude::*, Task};
+use napi_derive::napi;
+use realfft::RealFftPlanner;
+
+use crate::{full_params::Segment, WHISPER_SAMPLE_RATE};
+
+/// 25 ms analysis frames every 10 ms
+const FRAME_LEN: usize = 400;
+const FRAME_SHIFT: usize = 160;
+const FRAME_SHIFT_MS: u32 = 10;
+const N_FFT: usize = 512;
+const N_MELS: usize = 40;
+/// Cepstral coefficients without c0, the loudness says little about the voice
+const N_MFCC: usize = 19;
+/// Frames quieter than the loudest frame of the segment by this many decibels are pauses
+const SILENCE_DB: f32 = 30.0;
+/// Segments with fewer voiced frames are too short to tell, they take the speaker of a neighbour
+const MIN_VOICED_FRAMES: usize = 10;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct WhisperDiarizeOptions {
+  /// Number of speakers if known, estimated with `threshold` otherwise
+  pub n_speakers: Option<u32>,
+  /// Voices further apart than this are different speakers, in standard deviations of the features, 0.5 by default.
+  /// Lower values find more speakers
+  pub threshold: Option<f64>,
+}
+
+/// Number the speakers from 0, switching between the first two at every turn detected by tinydiarize
+pub(crate) fn alternate_speakers(segments: &mut [Segment]) {
+  let mut speaker = 0;
+  for segment in segments {
+    segment.speaker = Some(speaker);
+    if segment.speaker_turn_next {
+      speaker = 1 - speaker;
+    }
+  }
+}
+
+fn hz_to_mel(hz: f32) -> f32 {
+  2595.0 * (1.0 + hz / 700.0).log10()
+}
+
+fn mel_to_hz(mel: f32) -> f32 {
+  700.0 * (10f32.powf(mel / 2595.0) - 1.0)
+}
+
+/// Triangular filters between 20 Hz and 7600 Hz over the bins of the power spectrum
+fn mel_filters() -> Vec<Vec<f32>> {
+  let (low, high) = (hz_to_mel(20.0), hz_to_mel(7600.0));
+  let edges = (0..N_MELS + 2)
+    .map(|i| {
+      let mel = low + (high - low) * i as f32 / (N_MELS + 1) as f32;
+      mel_to_hz(mel) * N_FFT as f32 / WHISPER_SAMPLE_RATE as f32
+    })
+    .collect::<Vec<_>>();
+  edges
+    .windows(3)
+    .map(|edges| {
+      let (left, center, right) = (edges[0], edges[1], edges[2]);
+      (0..N_FFT / 2 + 1)
+        .map(|bin| {
+          let bin = bin as f32;
+          if bin > left && bin < center {
+            (bin - left) / (center - left)
+          } else if bin >= center && bin < right {
+            (right - bin) / (right - center)
+          } else {
+            0.0
+          }
+        })
+        .collect()
+    })
+    .collect()
+}
+
+struct Frame {
+  mfcc: [f32; N_MFCC],
+  energy_db: f32,
+}
+
+/// MFCC of every 10 ms of the audio
+fn frames(samples: &[f32]) -> Result<Vec<Frame>> {
+  let filters = mel_filters();
+  let window = (0..FRAME_LEN)
+    .map(|i| 0.54 - 0.46 * (2.0 * PI * i as f32 / (FRAME_LEN - 1) as f32).cos())
+    .collect::<Vec<_>>();
+  let dct = (1..=N_MFCC)
+    .map(|k| {
+      (0..N_MELS)
+        .map(|m| (PI * k as f32 * (m as f32 + 0.5) / N_MELS as f32).cos())
+        .collect::<Vec<_>>()
+    })
+    .collect::<Vec<_>>();
+  let fft = RealFftPlanner::<f32>::new().plan_fft_forward(N_FFT);
+  let mut input = fft.make_input_vec();
+  let mut spectrum = fft.make_output_vec();
+  let mut scratch = fft.make_scratch_vec();
+
+  let n_frames = match samples.len().checked_sub(FRAME_LEN) {
+    Some(n) => n / FRAME_SHIFT + 1,
+    None => 0,
+  };
+  let mut frames = Vec::with_capacity(n_frames);
+  for start in (0..n_frames).map(|i| i * FRAME_SHIFT) {
+    input.fill(0.0);
+    let mut energy = 0.0f32;
+    for (i, window) in window.iter().enumerate() {
+      let sample = samples[start + i];
+      let prev = (start + i).checked_sub(1).map_or(0.0, |prev| samples[prev]);
+      // pre-emphasis boosts the high frequencies where the voices differ the most
+      input[i] = (sample - 0.97 * prev) * window;
+      energy += sample * sample;
+    }
+    fft
+      .process_with_scratch(&mut input, &mut spectrum, &mut scratch)
+      .map_err(|err| Error::new(Status::GenericFailure, err.to_string()))?;
+    let log_mels = filters
+      .iter()
+      .map(|filter| {
+        let energy = filter
+          .iter()
+          .zip(&spectrum)
+          .map(|(weight, bin)| weight * bin.norm_sqr())
+          .sum::<f32>();
+        (energy + 1e-10).ln()
+      })
+      .collect::<Vec<_>>();
+    let mut mfcc = [0.0; N_MFCC];
+    for (coefficient, basis) in mfcc.iter_mut().zip(&dct) {
+      *coefficient = basis.iter().zip(&log_mels).map(|(a, b)| a * b).sum();
+    }
+    frames.push(Frame {
+      mfcc,
+      energy_db: 10.0 * (energy / FRAME_LEN as f32 + 1e-10).log10(),
+    });
+  }
+  Ok(frames)
+}
+
+/// Voiced frames of every segment
+fn voiced_frames(frames: &[Frame], segments: &[Segment]) -> Vec<Vec<usize>> {
+  segments
+    .iter()
+    .map(|segment| {
+      let start = ((segment.start / FRAME_SHIFT_MS) as usize).min(frames.len());
+      let end = ((segment.end / FRAME_SHIFT_MS) as usize).clamp(start, frames.len());
+      let loudest = frames[start..end]
+        .iter()
+        .map(|frame| frame.energy_db)
+        .fold(f32::NEG_INFINITY, f32::max);
+      (start..end)
+        .filter(|i| frames[*i].energy_db >= loudest - SILENCE_DB)
+        .collect()
+    })
+    .collect()
+}
+
+/// Sums and sums of squares of the coefficients, with the number of frames
+fn moments(frames: impl Iterator<Item = [f32; N_MFCC]>) -> ([f64; N_MFCC], [f64; N_MFCC], usize) {
+  let mut sum = [0.0f64; N_MFCC];
+  let mut sum_squares = [0.0f64; N_MFCC];
+  let mut count = 0;
+  for mfcc in frames {
+    for (k, coefficient) in mfcc.iter().enumerate() {
+      sum[k] += *coefficient as f64;
+      sum_squares[k] += (*coefficient as f64).powi(2);
+    }
+    count += 1;
+  }
+  (sum, sum_squares, count)
+}
+
+/// Mean and standard deviation of the MFCC of the voiced frames of every segment, like the statistics pooling of x-vectors.
+/// The MFCC are normalized over the whole recording to remove the channel. `None` for segments too short to tell.
+fn embeddings(frames: &[Frame], voiced: &[Vec<usize>]) -> Vec<Option<Vec<f32>>> {
+  let (sum, sum_squares, count) = moments(voiced.iter().flatten().map(|i| frames[*i].mfcc));
+  let count = count.max(1) as f64;
+  let mean = sum.map(|sum| sum / count);
+  let std: [f64; N_MFCC] =
+    std::array::from_fn(|k| (sum_squares[k] / count - mean[k].powi(2)).max(1e-10).sqrt());
+
+  voiced
+    .iter()
+    .map(|indices| {
+      if indices.len() < MIN_VOICED_FRAMES {
+        return None;
+      }
+      let (sum, sum_squares, count) = moments(indices.iter().map(|i| {
+        let mfcc = frames[*i].mfcc;
+        std::array::from_fn(|k| ((mfcc[k] as f64 - mean[k]) / std[k]) as f32)
+      }));
+      let segment_mean = sum.map(|sum| sum / count as f64);
+      let segment_std: [f64; N_MFCC] = std::array::from_fn(|k| {
+        (sum_squares[k] / count as f64 - segment_mean[k].powi(2))
+          .max(0.0)
+          .sqrt()
+      });
+      Some(
+        segment_mean
+          .iter()
+          .chain(&segment_std)
+          .map(|value| *value as f32)
+          .collect(),
+      )
+    })
+    .collect()
+}
+
+/// Root mean square of the differences, in standard deviations of the normalized features
+fn distance(a: &[f32], b: &[f32]) -> f32 {
+  let sum = a.iter().zip(b).map(|(a, b)| (a - b) * (a - b)).sum::<f32>();
+  (sum / a.len() as f32).sqrt()
+}
+
+/// Merges of average linkage agglomerative clustering sorted by distance,
+/// found in O(n²) with the nearest neighbor chain algorithm. The points are weighted by their number of frames.
+fn average_linkage(points: &[Vec<f32>], weights: &[f32]) -> Vec<(usize, usize, f32)> {
+  let n = points.len();
+  let mut dist = vec![0.0f32; n * n];
+  for (i, a) in points.iter().enumerate() {
+    for (j, b) in points.iter().enumerate().skip(i + 1) {
+      let d = distance(a, b);
+      dist[i * n + j] = d;
+      dist[j * n + i] = d;
+    }
+  }
+  let mut weights = weights.to_vec();
+  let mut active = vec![true; n];
+  let mut chain: Vec<usize> = Vec::new();
+  let mut merges = Vec::with_capacity(n.saturating_sub(1));
+  'merges: for _ in 1..n {
+    if chain.is_empty() {
+      chain.extend(active.iter().position(|active| *active));
+    }
+    loop {
+      let a = chain[chain.len() - 1];
+      // the previous cluster of the chain wins ties, otherwise the chain could cycle
+      let prev = chain.len().checked_sub(2).map(|i| chain[i]);
+      let mut nearest = prev.map(|prev| (prev, dist[a * n + prev]));
+      for k in (0..n).filter(|k| active[*k] && *k != a) {
+        if nearest.is_none_or(|(_, d)| dist[a * n + k] < d) {
+          nearest = Some((k, dist[a * n + k]));
+        }
+      }
+      let Some((b, d)) = nearest else {
+        break 'merges;
+      };
+      if Some(b) != prev {
+        chain.push(b);
+        continue;
+      }
+      chain.truncate(chain.len() - 2);
+      // the merged cluster replaces `a`, Lance-Williams update of the distances
+      for k in (0..n).filter(|k| active[*k] && *k != a && *k != b) {
+        let merged =
+          (weights[a] * dist[a * n + k] + weights[b] * dist[b * n + k]) / (weights[a] + weights[b]);
+        dist[a * n + k] = merged;
+        dist[k * n + a] = merged;
+      }
+      weights[a] += weights[b];
+      active[b] = false;
+      merges.push((a, b, d));
+      break;
+    }
+  }
+  merges.sort_by(|a, b| a.2.total_cmp(&b.2));
+  merges
+}
+
+fn find(parent: &mut [usize], mut i: usize) -> usize {
+  while parent[i] != i {
+    parent[i] = parent[parent[i]];
+    i = parent[i];
+  }
+  i
+}
+
+/// Speaker of every segment, numbered by first appearance
+fn cluster(
+  embeddings: Vec<Option<Vec<f32>>>,
+  weights: &[f32],
+  n_speakers: Option<usize>,
+  threshold: f32,
+) -> Vec<u32> {
+  let indices = (0..embeddings.len())
+    .filter(|i| embeddings[*i].is_some())
+    .collect::<Vec<_>>();
+  let points = embeddings.into_iter().flatten().collect::<Vec<_>>();
+  let point_weights = indices.iter().map(|i| weights[*i]).collect::<Vec<_>>();
+  let merges = average_linkage(&points, &point_weights);
+  let n_merges = match n_speakers {
+    Some(n_speakers) => points.len().saturating_sub(n_speakers.max(1)),
+    None => merges
+      .iter()
+      .take_while(|merge| merge.2 < threshold)
+      .count(),
+  };
+  let mut parent = (0..points.len()).collect::<Vec<_>>();
+  for (a, b, _) in &merges[..n_merges] {
+    let (a, b) = (find(&mut parent, *a), find(&mut parent, *b));
+    parent[b] = a;
+  }
+
+  let mut roots: Vec<usize> = Vec::new();
+  let mut speakers: Vec<Option<u32>> = vec![None; weights.len()];
+  for (point, segment) in indices.iter().enumerate() {
+    let root = find(&mut parent, point);
+    let speaker = match roots.iter().position(|known| *known == root) {
+      Some(speaker) => speaker,
+      None => {
+        roots.push(root);
+        roots.len() - 1
+      }
+    };
+    speakers[*segment] = Some(speaker as u32);
+  }
+  // the segments too short to tell take the speaker of the previous one, or of the first one
+  let first = speakers.iter().flatten().next().copied().unwrap_or(0);
+  let mut last = first;
+  speakers
+    .into_iter()
+    .map(|speaker| {
+      last = speaker.unwrap_or(last);
+      last
+    })
+    .collect()
+}
+
+fn diarize_segments(
+  samples: &[f32],
+  mut segments: Vec<Segment>,
+  options: &WhisperDiarizeOptions,
+) -> Result<Vec<Segment>> {
+  let frames = frames(samples)?;
+  let voiced = voiced_frames(&frames, &segments);
+  let weights = voiced
+    .iter()
+    .map(|indices| indices.len() as f32)
+    .collect::<Vec<_>>();
+  let speakers = cluster(
+    embeddings(&frames, &voiced),
+    &weights,
+    options.n_speakers.map(|n| n as usize),
+    options.threshold.unwrap_or(0.5) as f32,
+  );
+  for (segment, speaker) in segments.iter_mut().zip(speakers) {
+    segment.speaker = Some(speaker);
+  }
+  Ok(segments)
+}
+
+#[napi]
+/// Attach a `speaker` to the segments by clustering the voices of the 16 kHz mono samples they were transcribed from,
+/// e.g. the output of `decodeAudio`. Runs offline on the CPU, the speakers are numbered by first appearance.
+pub fn diarize(
+  samples: &[f32],
+  segments: Vec<Segment>,
+  options: Option<WhisperDiarizeOptions>,
+) -> Result<Vec<Segment>> {
+  diarize_segments(samples, segments, &options.unwrap_or_default())
+}
+
+pub struct DiarizeTask {
+  samples: Float32Array,
+  segments: Option<Vec<Segment>>,
+  options: WhisperDiarizeOptions,
+}
+
+#[napi]
+impl Task for DiarizeTask {
+  type Output = Vec<Segment>;
+  type JsValue = Vec<Segment>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    diarize_segments(
+      &self.samples,
+      self.segments.take().unwrap_or_default(),
+      &self.options,
+    )
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+#[napi]
+/// Async version of `diarize`, running on the libuv threadpool
+pub fn diarize_async(
+  samples: Float32Array,
+  segments: Vec<Segment>,
+  options: Option<WhisperDiarizeOptions>,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<DiarizeTask> {
+  AsyncTask::with_optional_signal(
+    DiarizeTask {
+      samples,
+      segments: Some(segments),
+      options: options.unwrap_or_default(),
+    },
+    signal,
+  )
+}
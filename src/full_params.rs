@@ -1,7 +1,11 @@
 use std::{
+  borrow::Cow,
   ffi::{c_int, c_void, CString},
   ptr,
-  sync::atomic::{AtomicPtr, Ordering},
+  sync::{
+    atomic::{AtomicBool, AtomicPtr, Ordering},
+    Arc, Mutex, MutexGuard,
+  },
 };
 
 use napi::{
@@ -11,11 +15,16 @@ use napi::{
 use napi_derive::napi;
 
 use crate::{
+  diarization::alternate_speakers,
+  grammar::Grammar,
+  logits_filter::{HotWords, LogitsFilter, TokenBias},
+  result::{ResultSource, Token},
   sys::{
-    whisper_beam_search_params, whisper_context, whisper_full_default_params, whisper_full_params,
-    whisper_sampling_strategy, whisper_state,
+    self, whisper_beam_search_params, whisper_context, whisper_full_default_params,
+    whisper_full_params, whisper_sampling_strategy, whisper_state, whisper_token_data,
   },
-  WhisperState,
+  vad::{Timeline, VadParams, WhisperVadParams},
+  WhisperContext, WhisperState,
 };
 
 const ON_ENCODER_BEGIN_CB_NAME: &str = "_onEncoderBegin";
@@ -55,12 +64,30 @@ impl From<whisper_sampling_strategy::WhisperSamplingStrategy> for WhisperSamplin
 
 #[napi(object)]
 pub struct WhisperGreedyParams {
+  /// Number of candidates sampled when the temperature is above 0
   pub best_of: i32,
 }
 
+impl From<WhisperGreedyParams> for sys::WhisperGreedyParams {
+  fn from(params: WhisperGreedyParams) -> Self {
+    Self {
+      best_of: params.best_of,
+    }
+  }
+}
+
+impl From<&sys::WhisperGreedyParams> for WhisperGreedyParams {
+  fn from(params: &sys::WhisperGreedyParams) -> Self {
+    Self {
+      best_of: params.best_of,
+    }
+  }
+}
+
 #[napi(object)]
 pub struct WhisperBeamSearchParams {
   pub beam_size: i32,
+  /// Not implemented in whisper.cpp, kept for compatibility with the reference implementation
   pub patience: f64,
 }
 
@@ -73,12 +100,53 @@ impl From<WhisperBeamSearchParams> for whisper_beam_search_params {
   }
 }
 
+impl From<&whisper_beam_search_params> for WhisperBeamSearchParams {
+  fn from(params: &whisper_beam_search_params) -> Self {
+    Self {
+      beam_size: params.beam_size,
+      patience: params.patience as f64,
+    }
+  }
+}
+
 #[napi(object)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Segment {
+  /// Index of the segment in the transcription
+  pub index: u32,
   pub text: String,
+  /// Start time in milliseconds
   pub start: u32,
+  /// End time in milliseconds
   pub end: u32,
+  /// Probability that the segment contains no speech
+  pub no_speech_prob: f64,
+  /// The next segment is predicted as a speaker turn, requires `tdrzEnable`
+  pub speaker_turn_next: bool,
+  /// Speaker of the segment starting at 0, alternating at the turns detected with `tdrzEnable` or clustered by `diarize`.
+  /// Counted per window by `WhisperStream`, not reported by `WhisperState.result`
+  pub speaker: Option<u32>,
+  /// Best-effort estimate of the temperature the segment was finally decoded at after the fallbacks,
+  /// whisper.cpp does not report it so it is counted from the decoding attempts of the window.
+  /// Not reported by `transcribeParallel` and `WhisperState.result`
+  pub temperature: Option<f64>,
+  pub tokens: Vec<Token>,
+}
+
+impl Segment {
+  /// Map the timestamps of the audio passed to whisper.cpp back to the audio passed by the user
+  pub(crate) fn map_timestamps(&mut self, timeline: &Timeline) {
+    self.start = timeline.map(self.start);
+    self.end = timeline.map(self.end);
+    for token in &mut self.tokens {
+      for timestamp in [&mut token.start, &mut token.end, &mut token.dtw_timestamp]
+        .into_iter()
+        .flatten()
+      {
+        *timestamp = timeline.map(*timestamp);
+      }
+    }
+  }
 }
 
 type OnStartCallback = ThreadsafeFunction<WhisperState, (), WhisperState, false>;
@@ -89,9 +157,74 @@ type AbortCallback = ThreadsafeFunction<(), (), (), false>;
 pub(crate) struct WhisperCallbackUserData {
   pub(crate) encoder_begin_callback: AtomicPtr<OnStartCallback>,
   pub(crate) new_segment_callback: AtomicPtr<SegmentCallback>,
+  /// Set by `Whisper.transcribeLong`, which passes the deduplicated segments to `onNewSegment` itself
+  hold_new_segments: AtomicBool,
   progress_callback: AtomicPtr<ProgressCallback>,
   abort_callback: AtomicPtr<AbortCallback>,
+  /// Set by the `AbortSignal` of the current run, installed by `WhisperFullParams::begin_run`
+  aborted: Mutex<Arc<AtomicBool>>,
   pub(crate) state: AtomicPtr<whisper_state>,
+  logits_filter: Mutex<LogitsFilter>,
+  /// Applied to the timestamps passed to `onNewSegment`
+  timeline: Mutex<Timeline>,
+  /// Part of the whole audio decoded by the current run, see `Whisper.transcribeLong`
+  progress_range: Mutex<ProgressRange>,
+}
+
+/// Maps the progress of a run on a chunk to the progress of the whole audio
+struct ProgressRange {
+  offset: f64,
+  scale: f64,
+  last: u32,
+}
+
+impl Default for ProgressRange {
+  fn default() -> Self {
+    Self {
+      offset: 0.0,
+      scale: 1.0,
+      last: 0,
+    }
+  }
+}
+
+impl ProgressRange {
+  fn map(&mut self, progress: c_int) -> u32 {
+    let progress = (self.offset + self.scale * progress.clamp(0, 100) as f64).round() as u32;
+    // the chunks overlap, the reported progress never goes back
+    self.last = self.last.max(progress.min(100));
+    self.last
+  }
+}
+
+impl WhisperCallbackUserData {
+  fn logits_filter(&self) -> MutexGuard<'_, LogitsFilter> {
+    self
+      .logits_filter
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn progress_range(&self) -> MutexGuard<'_, ProgressRange> {
+    self
+      .progress_range
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn timeline(&self) -> MutexGuard<'_, Timeline> {
+    self
+      .timeline
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn aborted(&self) -> MutexGuard<'_, Arc<AtomicBool>> {
+    self
+      .aborted
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
 }
 
 #[napi]
@@ -101,9 +234,26 @@ pub struct WhisperFullParams {
   suppress_regex: String,
   language: Option<CString>,
   initial_prompt: Option<CString>,
+  prompt_tokens: Option<Vec<i32>>,
+  grammar: Option<Grammar>,
+  vad: Option<VadParams>,
   pub(crate) callback_user_data: *mut WhisperCallbackUserData,
+  /// Set while a run uses the params, see `begin_run`
+  running: AtomicBool,
+}
+
+/// Marks the params as used by a run until dropped
+pub(crate) struct ParamsRun<'a>(&'a AtomicBool);
+
+impl Drop for ParamsRun<'_> {
+  fn drop(&mut self) {
+    self.0.store(false, Ordering::Release);
+  }
 }
 
+// The raw pointers are owned by the params and only read by whisper.cpp while a `full` call is running
+unsafe impl Send for WhisperFullParams {}
+
 impl Drop for WhisperFullParams {
   fn drop(&mut self) {
     unsafe {
@@ -134,6 +284,121 @@ impl Drop for WhisperFullParams {
   }
 }
 
+impl WhisperFullParams {
+  fn callback_user_data(&self) -> &WhisperCallbackUserData {
+    Box::leak(unsafe { Box::from_raw(self.callback_user_data) })
+  }
+
+  /// The fallback tracking, the timeline and the progress of a run are kept on the params,
+  /// so concurrent runs need their own params.
+  /// `aborted` is the flag of the abort signal of the run, see `watch_abort_signal`, a run without one gets a new flag.
+  pub(crate) fn begin_run(&self, aborted: Option<Arc<AtomicBool>>) -> Result<ParamsRun<'_>> {
+    if self.running.swap(true, Ordering::Acquire) {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "The params are used by another run, create separate WhisperFullParams for concurrent runs",
+      ));
+    }
+    *self.callback_user_data().aborted() = aborted.unwrap_or_default();
+    Ok(ParamsRun(&self.running))
+  }
+
+  /// Whether the signal of the current run was aborted
+  pub(crate) fn is_aborted(&self) -> bool {
+    self.callback_user_data().aborted().load(Ordering::Relaxed)
+  }
+
+  /// Reset the temperature fallback tracking before a run
+  pub(crate) fn reset_fallback(&self, enabled: bool) {
+    self
+      .callback_user_data()
+      .logits_filter()
+      .fallback
+      .reset(&self.inner, enabled);
+  }
+
+  /// Start of the window decoded by `WhisperStream`, added to the timestamps
+  pub(crate) fn set_time_offset(&self, offset_ms: u32) {
+    self.callback_user_data().timeline().offset_ms = offset_ms;
+  }
+
+  /// Stop passing the segments of the runs to `onNewSegment`, see `new_segment`
+  pub(crate) fn hold_new_segments(&self, hold: bool) {
+    self
+      .callback_user_data()
+      .hold_new_segments
+      .store(hold, Ordering::Relaxed);
+  }
+
+  /// Pass a segment to `onNewSegment`, its timestamps are already mapped
+  pub(crate) fn new_segment(&self, segment: Segment) {
+    let js_callback_ptr = self
+      .callback_user_data()
+      .new_segment_callback
+      .load(Ordering::Relaxed);
+    if js_callback_ptr.is_null() {
+      return;
+    }
+    let js_callback = Box::leak(unsafe { Box::from_raw(js_callback_ptr) });
+    js_callback.call(segment, ThreadsafeFunctionCallMode::NonBlocking);
+  }
+
+  /// Start the reported progress over, a new run reports from 0 again
+  pub(crate) fn restart_progress(&self) {
+    self.callback_user_data().progress_range().last = 0;
+  }
+
+  /// Report the progress of the next runs as the part `[start, end)` of the whole audio, in percent.
+  /// `None` reports the progress of every run on its own again.
+  pub(crate) fn set_progress_range(&self, range: Option<(f64, f64)>) {
+    let mut progress_range = self.callback_user_data().progress_range();
+    *progress_range = match range {
+      Some((start, end)) => ProgressRange {
+        offset: start,
+        scale: (end - start) / 100.0,
+        // the first chunk starts a new run, the following ones continue from the previous chunk
+        last: if start > 0.0 { progress_range.last } else { 0 },
+      },
+      None => ProgressRange::default(),
+    };
+  }
+
+  /// The audio passed to whisper.cpp, only the speech regions when the VAD is enabled
+  pub(crate) fn speech_samples<'a>(&self, samples: &'a [f32]) -> Cow<'a, [f32]> {
+    let mut timeline = self.callback_user_data().timeline();
+    match &self.vad {
+      Some(vad) => Cow::Owned(timeline.speech_only(samples, vad)),
+      None => {
+        timeline.clear_regions();
+        Cow::Borrowed(samples)
+      }
+    }
+  }
+
+  /// Prompt of the runs, `promptTokens` or the tokens of `initialPrompt` like whisper.cpp
+  pub(crate) fn prompt(&self, ctx: &WhisperContext) -> Result<Vec<i32>> {
+    match (&self.prompt_tokens, &self.initial_prompt) {
+      (Some(tokens), _) => Ok(tokens.clone()),
+      (None, Some(prompt)) => ctx.tokenize(&prompt.to_string_lossy()),
+      (None, None) => Ok(Vec::new()),
+    }
+  }
+
+  /// Fill in the temperatures, the original timestamps and the speakers of the segments of the last run
+  pub(crate) fn fill_segments(&self, segments: &mut [Segment]) {
+    let callback_user_data = self.callback_user_data();
+    let logits_filter = callback_user_data.logits_filter();
+    let timeline = callback_user_data.timeline();
+    for segment in segments.iter_mut() {
+      segment.temperature = logits_filter.fallback.temperature(segment.index as i32);
+      segment.map_timestamps(&timeline);
+    }
+    if self.inner.tdrz_enable {
+      alternate_speakers(segments);
+    }
+  }
+}
+
 #[napi]
 impl WhisperFullParams {
   #[napi(constructor)]
@@ -144,10 +409,15 @@ impl WhisperFullParams {
 
     let callback_user_data = Box::new(WhisperCallbackUserData {
       new_segment_callback: AtomicPtr::new(ptr::null_mut()),
+      hold_new_segments: AtomicBool::new(false),
       encoder_begin_callback: AtomicPtr::new(ptr::null_mut()),
       progress_callback: AtomicPtr::new(ptr::null_mut()),
       abort_callback: AtomicPtr::new(ptr::null_mut()),
+      aborted: Mutex::new(Arc::default()),
       state: AtomicPtr::new(ptr::null_mut()),
+      logits_filter: Mutex::new(LogitsFilter::default()),
+      timeline: Mutex::new(Timeline::default()),
+      progress_range: Mutex::new(ProgressRange::default()),
     });
     let callback_user_data_ptr = Box::into_raw(callback_user_data);
     params.new_segment_callback_user_data = callback_user_data_ptr.cast();
@@ -157,12 +427,18 @@ impl WhisperFullParams {
     params.progress_callback_user_data = callback_user_data_ptr.cast();
     params.abort_callback = Some(whisper_abort_callback);
     params.abort_callback_user_data = callback_user_data_ptr.cast();
+    params.logits_filter_callback = Some(whisper_logits_filter_callback);
+    params.logits_filter_callback_user_data = callback_user_data_ptr.cast();
     Ok(Self {
       inner: params,
       suppress_regex: String::new(),
       language: None,
       initial_prompt: None,
+      prompt_tokens: None,
+      grammar: None,
+      vad: None,
       callback_user_data: callback_user_data_ptr,
+      running: AtomicBool::new(false),
     })
   }
 
@@ -431,6 +707,29 @@ impl WhisperFullParams {
     self.initial_prompt = Some(c_value);
   }
 
+  #[napi(getter)]
+  /// Tokens to provide to the whisper decoder as the previous text, e.g. the tail of the previous chunk
+  pub fn get_prompt_tokens(&self) -> Option<Int32Array> {
+    self
+      .prompt_tokens
+      .as_ref()
+      .map(|tokens| Int32Array::new(tokens.clone()))
+  }
+
+  #[napi(setter)]
+  pub fn set_prompt_tokens(&mut self, value: Option<Int32Array>) {
+    let tokens = value.map(|tokens| tokens.to_vec()).unwrap_or_default();
+    if tokens.is_empty() {
+      self.inner.prompt_tokens = ptr::null();
+      self.inner.prompt_n_tokens = 0;
+      self.prompt_tokens = None;
+    } else {
+      self.inner.prompt_tokens = tokens.as_ptr();
+      self.inner.prompt_n_tokens = tokens.len() as i32;
+      self.prompt_tokens = Some(tokens);
+    }
+  }
+
   #[napi(getter)]
   pub fn get_on_encoder_begin(&self, this: This) -> Result<Function<Segment, ()>> {
     this.get_named_property_unchecked(ON_ENCODER_BEGIN_CB_NAME)
@@ -466,6 +765,86 @@ impl WhisperFullParams {
     self.inner.temperature = value as f32;
   }
 
+  #[napi(getter)]
+  /// Increase of the temperature when the decoding fails the thresholds below, 0 disables the fallback
+  pub fn get_temperature_inc(&self) -> f64 {
+    self.inner.temperature_inc as f64
+  }
+
+  #[napi(setter)]
+  pub fn set_temperature_inc(&mut self, value: f64) {
+    self.inner.temperature_inc = value as f32;
+  }
+
+  #[napi(getter)]
+  /// Fall back to a higher temperature when the entropy of the tokens is below, i.e. the text repeats itself
+  pub fn get_entropy_thold(&self) -> f64 {
+    self.inner.entropy_thold as f64
+  }
+
+  #[napi(setter)]
+  pub fn set_entropy_thold(&mut self, value: f64) {
+    self.inner.entropy_thold = value as f32;
+  }
+
+  #[napi(getter)]
+  /// Fall back to a higher temperature when the average log probability of the tokens is below
+  pub fn get_logprob_thold(&self) -> f64 {
+    self.inner.logprob_thold as f64
+  }
+
+  #[napi(setter)]
+  pub fn set_logprob_thold(&mut self, value: f64) {
+    self.inner.logprob_thold = value as f32;
+  }
+
+  #[napi(getter)]
+  /// Treat the window as silence when the no speech probability is above, and the log probability is below `logprobThold`
+  pub fn get_no_speech_thold(&self) -> f64 {
+    self.inner.no_speech_thold as f64
+  }
+
+  #[napi(setter)]
+  pub fn set_no_speech_thold(&mut self, value: f64) {
+    self.inner.no_speech_thold = value as f32;
+  }
+
+  #[napi(getter)]
+  /// Parameters of the `Greedy` strategy, returns a copy so assign a new object to change them
+  pub fn get_greedy(&self) -> WhisperGreedyParams {
+    (&self.inner.greedy).into()
+  }
+
+  #[napi(setter)]
+  pub fn set_greedy(&mut self, value: WhisperGreedyParams) -> Result<()> {
+    if value.best_of < 1 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "bestOf must be greater than 0",
+      ));
+    }
+    self.inner.greedy = value.into();
+    Ok(())
+  }
+
+  #[napi(getter)]
+  /// Parameters of the `BeamSearch` strategy, returns a copy so assign a new object to change them
+  pub fn get_beam_search(&self) -> WhisperBeamSearchParams {
+    (&self.inner.beam_search).into()
+  }
+
+  #[napi(setter)]
+  pub fn set_beam_search(&mut self, value: WhisperBeamSearchParams) -> Result<()> {
+    if value.beam_size < 1 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "beamSize must be greater than 0",
+      ));
+    }
+    self.inner.beam_search = value.into();
+    Ok(())
+  }
+
   #[napi(getter)]
   pub fn get_max_initial_ts(&self) -> f64 {
     self.inner.max_initial_ts as f64
@@ -486,6 +865,100 @@ impl WhisperFullParams {
     self.inner.length_penalty = value as f32;
   }
 
+  #[napi]
+  /// Constrain the decoding with a GBNF grammar, `startRule` defaults to "root".
+  /// Pass `null` to remove the grammar.
+  pub fn set_grammar(&mut self, grammar: Option<String>, start_rule: Option<String>) -> Result<()> {
+    let Some(grammar) = grammar else {
+      self.inner.grammar_rules = ptr::null();
+      self.inner.n_grammar_rules = 0;
+      self.inner.i_start_rule = 0;
+      self.grammar = None;
+      return Ok(());
+    };
+    let grammar = Grammar::parse(&grammar)?;
+    let start_rule = start_rule.as_deref().unwrap_or("root");
+    let i_start_rule = grammar.rule_id(start_rule).ok_or_else(|| {
+      Error::new(
+        Status::InvalidArg,
+        format!("Start rule `{start_rule}` is not defined in the grammar"),
+      )
+    })?;
+    self.inner.grammar_rules = grammar.rules().as_ptr();
+    self.inner.n_grammar_rules = grammar.n_rules();
+    self.inner.i_start_rule = i_start_rule;
+    self.grammar = Some(grammar);
+    Ok(())
+  }
+
+  #[napi(getter)]
+  /// Voice activity detection run before the decoding, only the speech regions are transcribed.
+  /// The timestamps of the segments are mapped back to the original audio, except for `WhisperState.result`.
+  pub fn get_vad(&self) -> Option<WhisperVadParams> {
+    self.vad.map(Into::into)
+  }
+
+  #[napi(setter)]
+  pub fn set_vad(&mut self, value: Option<WhisperVadParams>) -> Result<()> {
+    self.vad = value
+      .map(|params| VadParams::new(Some(params)))
+      .transpose()?;
+    Ok(())
+  }
+
+  #[napi(getter)]
+  /// Penalty applied to the logits of the tokens that do not match the grammar
+  pub fn get_grammar_penalty(&self) -> f64 {
+    self.inner.grammar_penalty as f64
+  }
+
+  #[napi(setter)]
+  pub fn set_grammar_penalty(&mut self, value: f64) {
+    self.inner.grammar_penalty = value as f32;
+  }
+
+  #[napi(getter)]
+  /// Biases added to the logits of the tokens at every step, see `hotWords` to boost words
+  pub fn get_token_bias(&self) -> Vec<TokenBias> {
+    self.callback_user_data().logits_filter().token_bias.clone()
+  }
+
+  #[napi(setter)]
+  pub fn set_token_bias(&mut self, value: Vec<TokenBias>) {
+    self.callback_user_data().logits_filter().token_bias = value;
+  }
+
+  #[napi(getter)]
+  /// Words to boost once they are started, e.g. product names that are misspelled
+  pub fn get_hot_words(&self) -> Option<HotWords> {
+    self.callback_user_data().logits_filter().hot_words.clone()
+  }
+
+  #[napi(setter)]
+  pub fn set_hot_words(&mut self, value: Option<HotWords>) {
+    self
+      .callback_user_data()
+      .logits_filter()
+      .set_hot_words(value);
+  }
+
+  #[napi(getter)]
+  /// Tokens that are never sampled
+  pub fn get_suppress_tokens(&self) -> Int32Array {
+    Int32Array::new(
+      self
+        .callback_user_data()
+        .logits_filter()
+        .suppress_tokens
+        .clone(),
+    )
+  }
+
+  #[napi(setter)]
+  pub fn set_suppress_tokens(&mut self, value: Int32Array) {
+    self.callback_user_data().logits_filter().suppress_tokens = value.to_vec();
+  }
+
   #[napi(setter, return_if_invalid)]
   pub fn set_on_encoder_begin(
     &mut self,
@@ -627,7 +1100,7 @@ impl WhisperFullParams {
 }
 
 extern "C" fn whisper_encoder_begin_callback(
-  _: *mut whisper_context,
+  ctx: *mut whisper_context,
   state: *mut whisper_state,
   user_data: *mut c_void,
 ) -> bool {
@@ -640,12 +1113,17 @@ extern "C" fn whisper_encoder_begin_callback(
     .encoder_begin_callback
     .load(Ordering::Relaxed);
   callback_user_data.state.store(state, Ordering::Relaxed);
+  // called once per window, before the decoding attempts
+  callback_user_data
+    .logits_filter()
+    .fallback
+    .begin_window(unsafe { sys::whisper_full_n_segments_from_state(state) });
   if js_callback_ptr.is_null() {
     return true;
   }
   let js_callback = Box::leak(unsafe { Box::from_raw(js_callback_ptr) });
   js_callback.call(
-    WhisperState { inner: state },
+    WhisperState::borrowed(ctx, state),
     ThreadsafeFunctionCallMode::NonBlocking,
   );
   true
@@ -666,7 +1144,8 @@ extern "C" fn whisper_progress_callback(
   let js_callback_ptr = callback_user_data.progress_callback.load(Ordering::Relaxed);
   if !js_callback_ptr.is_null() {
     let js_callback = Box::leak(unsafe { Box::from_raw(js_callback_ptr) });
-    js_callback.call(progress as _, ThreadsafeFunctionCallMode::NonBlocking);
+    let progress = callback_user_data.progress_range().map(progress);
+    js_callback.call(progress, ThreadsafeFunctionCallMode::NonBlocking);
   }
 }
 
@@ -676,8 +1155,6 @@ extern "C" fn whisper_new_segment_callback(
   n_new: c_int,
   user_data: *mut c_void,
 ) {
-  use crate::sys;
-
   if user_data.is_null() {
     return;
   }
@@ -687,38 +1164,22 @@ extern "C" fn whisper_new_segment_callback(
     .new_segment_callback
     .load(Ordering::Relaxed);
   callback_user_data.state.store(state, Ordering::Relaxed);
-  if js_callback_ptr.is_null() {
+  if js_callback_ptr.is_null() || callback_user_data.hold_new_segments.load(Ordering::Relaxed) {
     return;
   }
 
   let js_callback = Box::leak(unsafe { Box::from_raw(js_callback_ptr) });
 
-  let n_segments = unsafe { sys::whisper_full_n_segments(ctx) };
+  // The state is the one `whisper_full` is running on, which is not always the default state of the context
+  let result = ResultSource::State(ctx, state);
+  let n_segments = result.n_segments();
   let s0 = n_segments - n_new;
 
-  // Process each new segment with more careful text handling
   for i in s0..n_segments {
-    let t0 = unsafe { sys::whisper_full_get_segment_t0(ctx, i) };
-    let t1 = unsafe { sys::whisper_full_get_segment_t1(ctx, i) };
-
-    // Get the segment text
-    let text_ptr = unsafe { sys::whisper_full_get_segment_text(ctx, i) };
-    if text_ptr.is_null() {
-      continue;
-    }
-
-    // Convert text and clean it up
-    if let Ok(text) = unsafe { std::ffi::CStr::from_ptr(text_ptr).to_str() } {
-      let text = text.trim();
-      js_callback.call(
-        Segment {
-          text: text.to_string(),
-          start: t0 as u32,
-          end: t1 as u32,
-        },
-        ThreadsafeFunctionCallMode::NonBlocking,
-      );
-    }
+    let mut segment = result.segment(i);
+    segment.temperature = callback_user_data.logits_filter().fallback.temperature(i);
+    segment.map_timestamps(&callback_user_data.timeline());
+    js_callback.call(segment, ThreadsafeFunctionCallMode::NonBlocking);
   }
 }
 
@@ -729,6 +1190,9 @@ extern "C" fn whisper_abort_callback(user_data: *mut c_void) -> bool {
 
   let callback_user_data =
     Box::leak(unsafe { Box::from_raw(user_data.cast::<WhisperCallbackUserData>()) });
+  if callback_user_data.aborted().load(Ordering::Relaxed) {
+    return true;
+  }
   let js_callback_ptr = callback_user_data.abort_callback.load(Ordering::Relaxed);
   if js_callback_ptr.is_null() {
     return false;
@@ -737,3 +1201,31 @@ extern "C" fn whisper_abort_callback(user_data: *mut c_void) -> bool {
   js_callback.call((), ThreadsafeFunctionCallMode::NonBlocking);
   false
 }
+
+extern "C" fn whisper_logits_filter_callback(
+  ctx: *mut whisper_context,
+  _state: *mut whisper_state,
+  tokens: *const whisper_token_data,
+  n_tokens: c_int,
+  logits: *mut f32,
+  user_data: *mut c_void,
+) {
+  if user_data.is_null() || logits.is_null() {
+    return;
+  }
+  let callback_user_data =
+    Box::leak(unsafe { Box::from_raw(user_data.cast::<WhisperCallbackUserData>()) });
+  let mut logits_filter = callback_user_data.logits_filter();
+  logits_filter.fallback.decode_step(n_tokens);
+  let logits =
+    unsafe { std::slice::from_raw_parts_mut(logits, sys::whisper_n_vocab(ctx) as usize) };
+  let tokens = if tokens.is_null() || n_tokens <= 0 {
+    Vec::new()
+  } else {
+    unsafe { std::slice::from_raw_parts(tokens, n_tokens as usize) }
+      .iter()
+      .map(|token| token.id)
+      .collect()
+  };
+  logits_filter.apply(&tokens, logits);
+}
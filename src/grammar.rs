@@ -0,0 +1,317 @@
+// Port of whisper.cpp/examples/grammar-parser.cpp
+
+use std::collections::HashMap;
+
+use napi::{Error, Result, Status};
+
+use crate::sys::{whisper_grammar_element, WhisperGrammarElementType};
+
+/// Parsed GBNF grammar, in the layout expected by `whisper_full_params.grammar_rules`
+pub(crate) struct Grammar {
+  symbol_ids: HashMap<String, u32>,
+  rules: Vec<Vec<whisper_grammar_element>>,
+  rule_ptrs: Vec<*const whisper_grammar_element>,
+}
+
+impl Grammar {
+  pub(crate) fn parse(src: &str) -> Result<Self> {
+    let mut parser = Parser {
+      src,
+      symbol_ids: HashMap::new(),
+      rules: Vec::new(),
+    };
+    let mut pos = parser.parse_space(0, true);
+    while pos < src.len() {
+      pos = parser.parse_rule(pos)?;
+    }
+    let Parser {
+      symbol_ids,
+      mut rules,
+      ..
+    } = parser;
+    if rules.is_empty() {
+      return Err(grammar_error("grammar has no rules"));
+    }
+    // every referenced rule must be defined
+    rules.resize_with(symbol_ids.len(), Vec::new);
+    for (name, id) in &symbol_ids {
+      if rules[*id as usize].is_empty() {
+        return Err(grammar_error(format!("undefined rule identifier '{name}'")));
+      }
+    }
+    let rule_ptrs = rules.iter().map(|rule| rule.as_ptr()).collect();
+    Ok(Self {
+      symbol_ids,
+      rules,
+      rule_ptrs,
+    })
+  }
+
+  pub(crate) fn rule_id(&self, name: &str) -> Option<usize> {
+    self.symbol_ids.get(name).map(|id| *id as usize)
+  }
+
+  pub(crate) fn rules(&self) -> &[*const whisper_grammar_element] {
+    &self.rule_ptrs
+  }
+
+  pub(crate) fn n_rules(&self) -> usize {
+    self.rules.len()
+  }
+}
+
+fn grammar_error(message: impl AsRef<str>) -> Error {
+  Error::new(
+    Status::InvalidArg,
+    format!("Failed to parse grammar: {}", message.as_ref()),
+  )
+}
+
+fn element(type_: WhisperGrammarElementType, value: u32) -> whisper_grammar_element {
+  whisper_grammar_element { type_, value }
+}
+
+fn is_word_char(c: u8) -> bool {
+  c.is_ascii_alphanumeric() || c == b'-'
+}
+
+struct Parser<'a> {
+  src: &'a str,
+  symbol_ids: HashMap<String, u32>,
+  rules: Vec<Vec<whisper_grammar_element>>,
+}
+
+impl Parser<'_> {
+  /// The byte at `pos`, 0 at the end of the input like the NUL terminator in the C++ parser
+  fn byte(&self, pos: usize) -> u8 {
+    self.src.as_bytes().get(pos).copied().unwrap_or(0)
+  }
+
+  fn error_at(&self, message: &str, pos: usize) -> Error {
+    let rest = self.src.get(pos..).unwrap_or_default();
+    let rest = rest.lines().next().unwrap_or_default();
+    grammar_error(format!("{message} at '{rest}'"))
+  }
+
+  fn get_symbol_id(&mut self, name: &str) -> u32 {
+    let next_id = self.symbol_ids.len() as u32;
+    *self.symbol_ids.entry(name.to_owned()).or_insert(next_id)
+  }
+
+  fn generate_symbol_id(&mut self, base_name: &str) -> u32 {
+    let next_id = self.symbol_ids.len() as u32;
+    self
+      .symbol_ids
+      .insert(format!("{base_name}_{next_id}"), next_id);
+    next_id
+  }
+
+  fn add_rule(&mut self, rule_id: u32, rule: Vec<whisper_grammar_element>) {
+    let rule_id = rule_id as usize;
+    if self.rules.len() <= rule_id {
+      self.rules.resize_with(rule_id + 1, Vec::new);
+    }
+    self.rules[rule_id] = rule;
+  }
+
+  fn parse_space(&self, mut pos: usize, newline_ok: bool) -> usize {
+    loop {
+      match self.byte(pos) {
+        b'#' => {
+          while !matches!(self.byte(pos), 0 | b'\r' | b'\n') {
+            pos += 1;
+          }
+        }
+        b' ' | b'\t' => pos += 1,
+        b'\r' | b'\n' if newline_ok => pos += 1,
+        _ => return pos,
+      }
+    }
+  }
+
+  fn parse_name(&self, pos: usize) -> Result<usize> {
+    let mut end = pos;
+    while is_word_char(self.byte(end)) {
+      end += 1;
+    }
+    if end == pos {
+      return Err(self.error_at("expecting name", pos));
+    }
+    Ok(end)
+  }
+
+  fn parse_hex(&self, pos: usize, size: usize) -> Result<(u32, usize)> {
+    let digits = self
+      .src
+      .get(pos..pos + size)
+      .filter(|digits| digits.bytes().all(|c| c.is_ascii_hexdigit()))
+      .ok_or_else(|| self.error_at(&format!("expecting {size} hex chars"), pos))?;
+    let value = u32::from_str_radix(digits, 16).map_err(|_| self.error_at("invalid hex", pos))?;
+    Ok((value, pos + size))
+  }
+
+  fn parse_char(&self, pos: usize) -> Result<(u32, usize)> {
+    if self.byte(pos) == b'\\' {
+      return match self.byte(pos + 1) {
+        b'x' => self.parse_hex(pos + 2, 2),
+        b'u' => self.parse_hex(pos + 2, 4),
+        b'U' => self.parse_hex(pos + 2, 8),
+        b't' => Ok(('\t' as u32, pos + 2)),
+        b'r' => Ok(('\r' as u32, pos + 2)),
+        b'n' => Ok(('\n' as u32, pos + 2)),
+        c @ (b'\\' | b'"' | b'[' | b']') => Ok((c as u32, pos + 2)),
+        _ => Err(self.error_at("unknown escape", pos)),
+      };
+    }
+    match self.src.get(pos..).and_then(|rest| rest.chars().next()) {
+      Some(c) => Ok((c as u32, pos + c.len_utf8())),
+      None => Err(grammar_error("unexpected end of input")),
+    }
+  }
+
+  fn parse_sequence(
+    &mut self,
+    mut pos: usize,
+    rule_name: &str,
+    out_elements: &mut Vec<whisper_grammar_element>,
+    is_nested: bool,
+  ) -> Result<usize> {
+    let mut last_sym_start = out_elements.len();
+    loop {
+      match self.byte(pos) {
+        // literal string
+        b'"' => {
+          pos += 1;
+          last_sym_start = out_elements.len();
+          while self.byte(pos) != b'"' {
+            let (c, next) = self.parse_char(pos)?;
+            pos = next;
+            out_elements.push(element(WhisperGrammarElementType::Char, c));
+          }
+          pos = self.parse_space(pos + 1, is_nested);
+        }
+        // char range(s)
+        b'[' => {
+          pos += 1;
+          let mut start_type = WhisperGrammarElementType::Char;
+          if self.byte(pos) == b'^' {
+            pos += 1;
+            start_type = WhisperGrammarElementType::CharNot;
+          }
+          last_sym_start = out_elements.len();
+          while self.byte(pos) != b']' {
+            let (c, next) = self.parse_char(pos)?;
+            pos = next;
+            let type_ = if last_sym_start < out_elements.len() {
+              WhisperGrammarElementType::CharAlt
+            } else {
+              start_type
+            };
+            out_elements.push(element(type_, c));
+            if self.byte(pos) == b'-' && self.byte(pos + 1) != b']' {
+              let (end_c, next) = self.parse_char(pos + 1)?;
+              pos = next;
+              out_elements.push(element(WhisperGrammarElementType::CharRngUpper, end_c));
+            }
+          }
+          pos = self.parse_space(pos + 1, is_nested);
+        }
+        // rule reference
+        c if is_word_char(c) => {
+          let src = self.src;
+          let name_end = self.parse_name(pos)?;
+          let ref_rule_id = self.get_symbol_id(&src[pos..name_end]);
+          pos = self.parse_space(name_end, is_nested);
+          last_sym_start = out_elements.len();
+          out_elements.push(element(WhisperGrammarElementType::RuleRef, ref_rule_id));
+        }
+        // grouping
+        b'(' => {
+          // parse nested alternates into synthesized rule
+          pos = self.parse_space(pos + 1, true);
+          let sub_rule_id = self.generate_symbol_id(rule_name);
+          pos = self.parse_alternates(pos, rule_name, sub_rule_id, true)?;
+          last_sym_start = out_elements.len();
+          // output reference to synthesized rule
+          out_elements.push(element(WhisperGrammarElementType::RuleRef, sub_rule_id));
+          if self.byte(pos) != b')' {
+            return Err(self.error_at("expecting ')'", pos));
+          }
+          pos = self.parse_space(pos + 1, is_nested);
+        }
+        // repetition operator
+        op @ (b'*' | b'+' | b'?') => {
+          if last_sym_start == out_elements.len() {
+            return Err(self.error_at("expecting preceding item to */+/?", pos));
+          }
+          // apply transformation to previous symbol (last_sym_start to end) according to rewrite rules:
+          // S* --> S' ::= S S' |
+          // S+ --> S' ::= S S' | S
+          // S? --> S' ::= S |
+          let sub_rule_id = self.generate_symbol_id(rule_name);
+          let mut sub_rule = out_elements[last_sym_start..].to_vec();
+          if op != b'?' {
+            // cause generated rule to recurse
+            sub_rule.push(element(WhisperGrammarElementType::RuleRef, sub_rule_id));
+          }
+          // mark start of alternate def
+          sub_rule.push(element(WhisperGrammarElementType::Alt, 0));
+          if op == b'+' {
+            // add preceding symbol as alternate only for '+' (otherwise empty)
+            sub_rule.extend_from_slice(&out_elements[last_sym_start..]);
+          }
+          sub_rule.push(element(WhisperGrammarElementType::End, 0));
+          self.add_rule(sub_rule_id, sub_rule);
+
+          // in original rule, replace previous symbol with reference to generated rule
+          out_elements.truncate(last_sym_start);
+          out_elements.push(element(WhisperGrammarElementType::RuleRef, sub_rule_id));
+
+          pos = self.parse_space(pos + 1, is_nested);
+        }
+        _ => return Ok(pos),
+      }
+    }
+  }
+
+  fn parse_alternates(
+    &mut self,
+    pos: usize,
+    rule_name: &str,
+    rule_id: u32,
+    is_nested: bool,
+  ) -> Result<usize> {
+    let mut rule = Vec::new();
+    let mut pos = self.parse_sequence(pos, rule_name, &mut rule, is_nested)?;
+    while self.byte(pos) == b'|' {
+      rule.push(element(WhisperGrammarElementType::Alt, 0));
+      pos = self.parse_space(pos + 1, true);
+      pos = self.parse_sequence(pos, rule_name, &mut rule, is_nested)?;
+    }
+    rule.push(element(WhisperGrammarElementType::End, 0));
+    self.add_rule(rule_id, rule);
+    Ok(pos)
+  }
+
+  fn parse_rule(&mut self, pos: usize) -> Result<usize> {
+    let src = self.src;
+    let name_end = self.parse_name(pos)?;
+    let mut next = self.parse_space(name_end, false);
+    let name = &src[pos..name_end];
+    let rule_id = self.get_symbol_id(name);
+
+    if !src[next..].starts_with("::=") {
+      return Err(self.error_at("expecting ::=", next));
+    }
+    next = self.parse_space(next + 3, true);
+    next = self.parse_alternates(next, name, rule_id, false)?;
+
+    match self.byte(next) {
+      b'\r' => next += if self.byte(next + 1) == b'\n' { 2 } else { 1 },
+      b'\n' => next += 1,
+      0 => {}
+      _ => return Err(self.error_at("expecting newline or end", next)),
+    }
+    Ok(self.parse_space(next, true))
+  }
+}
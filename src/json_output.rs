@@ -0,0 +1,288 @@
+// JSON output of whisper.cpp/examples/cli/cli.cpp, `-oj` and `-ojf`
+
+use std::{
+  ffi::{c_char, CStr},
+  fmt::{self, Write},
+};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{
+  full_params::{Segment, WhisperFullParams},
+  result::WhisperFullResult,
+  sys, Whisper,
+};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct WhisperJsonOptions {
+  /// Written to `params.model`, the model path passed to the whisper.cpp CLI
+  pub model: Option<String>,
+  /// Include the tokens of the segments like `-ojf`, true by default. `false` matches `-oj`
+  pub full: Option<bool>,
+}
+
+fn c_str(value: *const c_char) -> String {
+  if value.is_null() {
+    return String::new();
+  }
+  unsafe { CStr::from_ptr(value) }
+    .to_string_lossy()
+    .into_owned()
+}
+
+/// `HH:MM:SS,mmm`
+fn timestamp(ms: u32) -> String {
+  format!(
+    "{:02}:{:02}:{:02},{:03}",
+    ms / 3_600_000,
+    ms / 60_000 % 60,
+    ms / 1000 % 60,
+    ms % 1000
+  )
+}
+
+/// Same as `std::ostream << float`, i.e. `%g` with 6 significant digits
+fn format_float(value: f64) -> String {
+  if !value.is_finite() {
+    return value.to_string().to_lowercase();
+  }
+  fn trim_zeros(s: &str) -> &str {
+    if s.contains('.') {
+      s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+      s
+    }
+  }
+  let scientific = format!("{value:.5e}");
+  let (mantissa, exp) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+  let exp = exp.parse::<i32>().unwrap_or(0);
+  if !(-4..6).contains(&exp) {
+    let sign = if exp < 0 { '-' } else { '+' };
+    format!("{}e{sign}{:02}", trim_zeros(mantissa), exp.abs())
+  } else {
+    trim_zeros(&format!("{value:.*}", (5 - exp) as usize)).to_string()
+  }
+}
+
+/// Writes the same layout as the CLI, one value per line indented with tabs
+struct JsonWriter {
+  output: String,
+  indent: usize,
+}
+
+impl JsonWriter {
+  fn line(&mut self, line: fmt::Arguments) {
+    for _ in 0..self.indent {
+      self.output.push('\t');
+    }
+    let _ = self.output.write_fmt(line);
+    self.output.push('\n');
+  }
+
+  fn start_obj(&mut self, name: Option<&str>) {
+    match name {
+      Some(name) => self.line(format_args!("\"{name}\": {{")),
+      None => self.line(format_args!("{{")),
+    }
+    self.indent += 1;
+  }
+
+  fn end_obj(&mut self, end: bool) {
+    self.indent -= 1;
+    self.line(format_args!("{}", if end { "}" } else { "}," }));
+  }
+
+  fn start_arr(&mut self, name: &str) {
+    self.line(format_args!("\"{name}\": ["));
+    self.indent += 1;
+  }
+
+  fn end_arr(&mut self, end: bool) {
+    self.indent -= 1;
+    self.line(format_args!("{}", if end { "]" } else { "]," }));
+  }
+
+  fn value(&mut self, name: &str, value: impl fmt::Display, end: bool) {
+    self.line(format_args!(
+      "\"{name}\": {value}{}",
+      if end { "" } else { "," }
+    ));
+  }
+
+  fn value_s(&mut self, name: &str, value: &str, end: bool) {
+    let mut escaped = String::with_capacity(value.len() + 2);
+    escaped.push('"');
+    for c in value.chars() {
+      match c {
+        '"' => escaped.push_str("\\\""),
+        '\\' => escaped.push_str("\\\\"),
+        '\n' => escaped.push_str("\\n"),
+        '\r' => escaped.push_str("\\r"),
+        '\t' => escaped.push_str("\\t"),
+        c if c.is_control() => {
+          let _ = write!(escaped, "\\u{:04x}", c as u32);
+        }
+        c => escaped.push(c),
+      }
+    }
+    escaped.push('"');
+    self.value(name, escaped, end);
+  }
+
+  fn times_o(&mut self, start: u32, end: u32, last: bool) {
+    self.start_obj(Some("timestamps"));
+    self.value_s("from", &timestamp(start), false);
+    self.value_s("to", &timestamp(end), true);
+    self.end_obj(false);
+    self.start_obj(Some("offsets"));
+    self.value("from", start, false);
+    self.value("to", end, true);
+    self.end_obj(last);
+  }
+}
+
+/// Untrimmed text of the segment like `whisper_full_get_segment_text`, rebuilt from its text tokens
+fn raw_text(segment: &Segment, eot: i32) -> String {
+  let text = segment
+    .tokens
+    .iter()
+    .filter(|token| token.id < eot)
+    .map(|token| token.text.as_str())
+    .collect::<String>();
+  // a multi-byte character split across tokens is not decoded by the tokens alone
+  if text.trim() == segment.text {
+    text
+  } else {
+    segment.text.clone()
+  }
+}
+
+fn format(
+  ctx: *mut sys::whisper_context,
+  params: &WhisperFullParams,
+  result: &WhisperFullResult,
+  options: &WhisperJsonOptions,
+) -> String {
+  let full = options.full.unwrap_or(true);
+  let tdrz = params.inner.tdrz_enable;
+  let eot = unsafe { sys::whisper_token_eot(ctx) };
+  let mut json = JsonWriter {
+    output: String::new(),
+    indent: 0,
+  };
+
+  json.start_obj(None);
+  json.value_s(
+    "systeminfo",
+    &c_str(unsafe { sys::whisper_print_system_info() }),
+    false,
+  );
+  json.start_obj(Some("model"));
+  unsafe {
+    json.value_s("type", &c_str(sys::whisper_model_type_readable(ctx)), false);
+    json.value(
+      "multilingual",
+      sys::whisper_is_multilingual(ctx) != 0,
+      false,
+    );
+    json.value("vocab", sys::whisper_model_n_vocab(ctx), false);
+    json.start_obj(Some("audio"));
+    json.value("ctx", sys::whisper_model_n_audio_ctx(ctx), false);
+    json.value("state", sys::whisper_model_n_audio_state(ctx), false);
+    json.value("head", sys::whisper_model_n_audio_head(ctx), false);
+    json.value("layer", sys::whisper_model_n_audio_layer(ctx), true);
+    json.end_obj(false);
+    json.start_obj(Some("text"));
+    json.value("ctx", sys::whisper_model_n_text_ctx(ctx), false);
+    json.value("state", sys::whisper_model_n_text_state(ctx), false);
+    json.value("head", sys::whisper_model_n_text_head(ctx), false);
+    json.value("layer", sys::whisper_model_n_text_layer(ctx), true);
+    json.end_obj(false);
+    json.value("mels", sys::whisper_model_n_mels(ctx), false);
+    json.value("ftype", sys::whisper_model_ftype(ctx), true);
+  }
+  json.end_obj(false);
+  json.start_obj(Some("params"));
+  json.value_s("model", options.model.as_deref().unwrap_or_default(), false);
+  json.value_s("language", &c_str(params.inner.language), false);
+  json.value("translate", params.inner.translate, true);
+  json.end_obj(false);
+  json.start_obj(Some("result"));
+  let language = match &result.language {
+    Some(language) => language.clone(),
+    None => c_str(unsafe { sys::whisper_lang_str(result.lang_id) }),
+  };
+  json.value_s("language", &language, true);
+  json.end_obj(false);
+
+  json.start_arr("transcription");
+  for (i, segment) in result.segments.iter().enumerate() {
+    let speaker = segment.speaker.is_some();
+    json.start_obj(None);
+    json.times_o(segment.start, segment.end, false);
+    json.value_s("text", &raw_text(segment, eot), !speaker && !tdrz && !full);
+    if full {
+      json.start_arr("tokens");
+      for (j, token) in segment.tokens.iter().enumerate() {
+        json.start_obj(None);
+        json.value_s("text", &token.text, false);
+        if let (Some(start), Some(end)) = (token.start, token.end) {
+          json.times_o(start, end, false);
+        }
+        json.value("id", token.id, false);
+        json.value("p", format_float(token.p), false);
+        // whisper.cpp reports the DTW timestamp in units of 10 ms, -1 if it was not computed
+        let t_dtw = token.dtw_timestamp.map_or(-1, |ms| ms as i64 / 10);
+        json.value("t_dtw", format_float(t_dtw as f64), true);
+        json.end_obj(j + 1 == segment.tokens.len());
+      }
+      json.end_arr(!speaker && !tdrz);
+    }
+    if let Some(speaker) = segment.speaker {
+      // the stereo diarization of the CLI writes the speaker as a string too
+      json.value_s("speaker", &speaker.to_string(), !tdrz);
+    }
+    if tdrz {
+      json.value("speaker_turn_next", segment.speaker_turn_next, true);
+    }
+    json.end_obj(i + 1 == result.segments.len());
+  }
+  json.end_arr(true);
+  json.end_obj(true);
+  json.output
+}
+
+#[napi]
+impl Whisper {
+  #[napi]
+  /// Serialize a result of this model like the `-ojf` output of the whisper.cpp CLI.
+  /// `params` are the params the result was transcribed with.
+  pub fn format_json(
+    &self,
+    params: &WhisperFullParams,
+    result: WhisperFullResult,
+    options: Option<WhisperJsonOptions>,
+  ) -> String {
+    format(
+      self.ctx.inner,
+      params,
+      &result,
+      &options.unwrap_or_default(),
+    )
+  }
+
+  #[napi]
+  /// Write the output of `formatJson` to `path`
+  pub fn write_json(
+    &self,
+    path: String,
+    params: &WhisperFullParams,
+    result: WhisperFullResult,
+    options: Option<WhisperJsonOptions>,
+  ) -> Result<()> {
+    std::fs::write(path, self.format_json(params, result, options))?;
+    Ok(())
+  }
+}
@@ -0,0 +1,180 @@
+use std::{collections::HashMap, ffi::CStr, sync::Arc};
+
+use napi::{bindgen_prelude::*, Task};
+use napi_derive::napi;
+
+use crate::{sys, Whisper, WhisperContext, WhisperState};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct DetectLanguageOptions {
+  /// Offset of the audio to detect the language from, in milliseconds
+  pub offset_ms: Option<i32>,
+  pub n_threads: Option<i32>,
+}
+
+#[napi(object)]
+pub struct LanguageDetection {
+  /// Id of the most probable language
+  pub lang_id: i32,
+  /// Short string of the most probable language, e.g. "en"
+  pub language: String,
+  pub probability: f64,
+  /// Probability of every language, keyed by the short string of the language
+  pub probabilities: HashMap<String, f64>,
+}
+
+impl WhisperContext {
+  /// Detect the language on `state`, or on the default state of the context when it is `None`.
+  /// The caller holds the lock of the state.
+  pub(crate) fn detect_language(
+    &self,
+    state: Option<&WhisperState>,
+    samples: &[f32],
+    offset_ms: i32,
+    n_threads: i32,
+  ) -> Result<LanguageDetection> {
+    let status = unsafe {
+      match state {
+        Some(state) => sys::whisper_pcm_to_mel_with_state(
+          self.inner,
+          state.inner,
+          samples.as_ptr(),
+          samples.len() as i32,
+          n_threads,
+        ),
+        None => sys::whisper_pcm_to_mel(
+          self.inner,
+          samples.as_ptr(),
+          samples.len() as i32,
+          n_threads,
+        ),
+      }
+    };
+    if status != 0 {
+      return Err(Error::new(
+        Status::GenericFailure,
+        format!("Failed to compute the mel spectrogram: {status}"),
+      ));
+    }
+    let mut lang_probs = vec![0f32; unsafe { sys::whisper_lang_max_id() } as usize + 1];
+    let lang_id = unsafe {
+      match state {
+        Some(state) => sys::whisper_lang_auto_detect_with_state(
+          self.inner,
+          state.inner,
+          offset_ms,
+          n_threads,
+          lang_probs.as_mut_ptr(),
+        ),
+        None => {
+          sys::whisper_lang_auto_detect(self.inner, offset_ms, n_threads, lang_probs.as_mut_ptr())
+        }
+      }
+    };
+    if lang_id < 0 {
+      return Err(Error::new(
+        Status::GenericFailure,
+        format!("Failed to auto detect the language: {lang_id}"),
+      ));
+    }
+    let probabilities = lang_probs
+      .iter()
+      .enumerate()
+      .filter_map(|(id, p)| Some((lang_str(id as i32)?, *p as f64)))
+      .collect::<HashMap<_, _>>();
+    let language = lang_str(lang_id).unwrap_or_default();
+    Ok(LanguageDetection {
+      lang_id,
+      probability: probabilities.get(&language).copied().unwrap_or_default(),
+      language,
+      probabilities,
+    })
+  }
+}
+
+fn lang_str(id: i32) -> Option<String> {
+  let lang = unsafe { sys::whisper_lang_str(id) };
+  if lang.is_null() {
+    return None;
+  }
+  Some(
+    unsafe { CStr::from_ptr(lang) }
+      .to_string_lossy()
+      .into_owned(),
+  )
+}
+
+fn default_n_threads() -> i32 {
+  // same as `whisper_full_default_params`
+  std::thread::available_parallelism()
+    .map(|n| n.get().min(4) as i32)
+    .unwrap_or(4)
+}
+
+#[napi]
+impl Whisper {
+  #[napi]
+  /// Detect the spoken language of the first 30 seconds after `offsetMs`, without running the decoder
+  pub fn detect_language(
+    &self,
+    samples: &[f32],
+    options: Option<DetectLanguageOptions>,
+  ) -> Result<LanguageDetection> {
+    let options = options.unwrap_or_default();
+    let _guard = self.ctx.try_lock_state(self.state.as_deref())?;
+    self.ctx.detect_language(
+      self.state.as_deref(),
+      samples,
+      options.offset_ms.unwrap_or(0),
+      options.n_threads.unwrap_or_else(default_n_threads),
+    )
+  }
+
+  #[napi]
+  /// Async version of `detectLanguage`, running on the libuv threadpool
+  pub fn detect_language_async(
+    &self,
+    samples: Float32Array,
+    options: Option<DetectLanguageOptions>,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<DetectLanguageTask> {
+    let options = options.unwrap_or_default();
+    AsyncTask::with_optional_signal(
+      DetectLanguageTask {
+        ctx: self.ctx.clone(),
+        state: self.state.clone(),
+        samples,
+        offset_ms: options.offset_ms.unwrap_or(0),
+        n_threads: options.n_threads.unwrap_or_else(default_n_threads),
+      },
+      signal,
+    )
+  }
+}
+
+pub struct DetectLanguageTask {
+  ctx: Arc<WhisperContext>,
+  state: Option<Arc<WhisperState>>,
+  samples: Float32Array,
+  offset_ms: i32,
+  n_threads: i32,
+}
+
+#[napi]
+impl Task for DetectLanguageTask {
+  type Output = LanguageDetection;
+  type JsValue = LanguageDetection;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let state = self.state.as_deref();
+    let _guard = self.ctx.lock_state(state);
+    self
+      .ctx
+      .detect_language(state, &self.samples, self.offset_ms, self.n_threads)
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
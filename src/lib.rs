@@ -1,25 +1,58 @@
 #![deny(clippy::all)]
 
-use std::{borrow::Cow, ffi::CString, fs::File, io::Read, ptr, sync::atomic::Ordering};
+use std::{
+  ffi::CString,
+  ptr,
+  sync::{
+    atomic::{AtomicBool, AtomicPtr, Ordering},
+    Arc, Mutex, MutexGuard, TryLockError,
+  },
+};
 
 use napi::{
   bindgen_prelude::*,
   threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
+  Task,
 };
 use napi_derive::{module_init, napi};
 
 pub use audio_decode::{decode_audio, decode_audio_async};
-use context_params::WhisperContextParams;
+use context_params::{ContextParams, WhisperContextParams};
+pub use diarization::{diarize, diarize_async};
 use full_params::{WhisperCallbackUserData, WhisperFullParams};
+pub use model_header::{inspect_model, inspect_model_async};
+pub use model_loader::WhisperModelStream;
+pub use quantize::{quantize_model, quantize_model_async};
+use result::{ResultSource, WhisperFullResult};
 pub use state::WhisperState;
+pub use stream::WhisperStream;
+pub use subtitles::{format_subtitles, write_subtitles};
+pub use vad::detect_speech;
 pub use video::split_audio_from_video;
+pub use words::split_words;
 
 mod audio_decode;
 mod context_params;
+mod diarization;
 mod full_params;
+mod grammar;
+mod json_output;
+mod language;
+mod logits_filter;
+mod long;
+mod model_cache;
+mod model_header;
+mod model_loader;
+mod quantize;
+mod result;
 mod state;
+mod stream;
+mod subtitles;
 mod sys;
+mod tokenizer;
+mod vad;
 mod video;
+mod words;
 
 #[cfg(not(target_arch = "arm"))]
 #[global_allocator]
@@ -32,13 +65,193 @@ fn init() {
   unsafe { sys::whisper_log_set(Some(whisper_logger_callback), ptr::null_mut()) };
 }
 
-#[napi]
-pub struct Whisper {
-  inner: *mut sys::whisper_context,
-  callback_user_data: *mut WhisperCallbackUserData,
+pub(crate) struct WhisperContext {
+  pub(crate) inner: *mut sys::whisper_context,
+  // `whisper_full` without an explicit state runs on the context's default state,
+  // so calls from the JS thread and the libuv threadpool must not overlap
+  default_state_lock: Mutex<()>,
+  /// Custom DTW alignment heads, read by whisper.cpp whenever a state is created
+  _aheads: Vec<sys::whisper_ahead>,
 }
 
-impl Drop for Whisper {
+unsafe impl Send for WhisperContext {}
+unsafe impl Sync for WhisperContext {}
+
+impl WhisperContext {
+  pub(crate) fn new(
+    inner: *mut sys::whisper_context,
+    params: ContextParams,
+    error_message: &str,
+  ) -> Result<Self> {
+    if inner.is_null() {
+      return Err(Error::new(Status::InvalidArg, error_message));
+    }
+    Ok(Self {
+      inner,
+      default_state_lock: Mutex::new(()),
+      _aheads: params.aheads,
+    })
+  }
+
+  /// Load the model from a buffer or a file path, a path is read by whisper.cpp without an intermediate copy
+  pub(crate) fn load(model: Either<&[u8], &str>, params: ContextParams) -> Result<Self> {
+    match model {
+      Either::A(buf) => {
+        let inner = unsafe {
+          sys::whisper_init_from_buffer_with_params(buf.as_ptr().cast(), buf.len(), params.inner)
+        };
+        WhisperContext::new(
+          inner,
+          params,
+          "Failed to initialize Whisper model from buffer",
+        )
+      }
+      Either::B(filepath) => {
+        let c_filepath = CString::new(filepath)?;
+        let inner =
+          unsafe { sys::whisper_init_from_file_with_params(c_filepath.as_ptr(), params.inner) };
+        WhisperContext::new(
+          inner,
+          params,
+          "Failed to initialize Whisper model from file",
+        )
+      }
+    }
+  }
+
+  pub(crate) fn lock_default_state(&self) -> MutexGuard<'_, ()> {
+    self
+      .default_state_lock
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  /// Lock `state`, or the default state of the context when it is `None`
+  pub(crate) fn lock_state<'a>(&'a self, state: Option<&'a WhisperState>) -> MutexGuard<'a, ()> {
+    match state {
+      Some(state) => state.lock(),
+      None => self.lock_default_state(),
+    }
+  }
+
+  /// Same as `lock_state` for the JS thread, fails instead of waiting for a run on the threadpool
+  pub(crate) fn try_lock_state<'a>(
+    &'a self,
+    state: Option<&'a WhisperState>,
+  ) -> Result<MutexGuard<'a, ()>> {
+    match state {
+      Some(state) => state.try_lock(),
+      None => try_lock_run(&self.default_state_lock),
+    }
+  }
+
+  pub(crate) fn result_source(&self, state: Option<&WhisperState>) -> ResultSource {
+    match state {
+      Some(state) => state.result_source(),
+      None => ResultSource::Context(self.inner),
+    }
+  }
+
+  /// Run on `state`, or on the default state of the context when it is `None`
+  fn full(
+    &self,
+    state: Option<&WhisperState>,
+    params: &WhisperFullParams,
+    samples: &[f32],
+  ) -> Result<()> {
+    params.restart_progress();
+    self.full_with_prompt(state, params, samples, None)
+  }
+
+  /// `prompt_tokens` replace the prompt of the params, e.g. with the text of the previous chunk
+  fn full_with_prompt(
+    &self,
+    state: Option<&WhisperState>,
+    params: &WhisperFullParams,
+    samples: &[f32],
+    prompt_tokens: Option<&[i32]>,
+  ) -> Result<()> {
+    params.reset_fallback(true);
+    let samples = params.speech_samples(samples);
+    let mut inner = params.inner.clone();
+    if let Some(prompt_tokens) = prompt_tokens {
+      inner.prompt_tokens = prompt_tokens.as_ptr();
+      inner.prompt_n_tokens = prompt_tokens.len() as i32;
+    }
+    let status = unsafe {
+      match state {
+        Some(state) => sys::whisper_full_with_state(
+          self.inner,
+          state.inner,
+          inner,
+          samples.as_ptr().cast(),
+          samples.len() as i32,
+        ),
+        None => sys::whisper_full(
+          self.inner,
+          inner,
+          samples.as_ptr().cast(),
+          samples.len() as i32,
+        ),
+      }
+    };
+    check_full_status(status, params)
+  }
+
+  fn full_parallel(
+    &self,
+    params: &WhisperFullParams,
+    samples: &[f32],
+    n_processors: u32,
+  ) -> Result<()> {
+    if n_processors == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "nProcessors must be greater than 0",
+      ));
+    }
+    params.reset_fallback(false);
+    params.restart_progress();
+    let samples = params.speech_samples(samples);
+    let status = unsafe {
+      sys::whisper_full_parallel(
+        self.inner,
+        params.inner.clone(),
+        samples.as_ptr().cast(),
+        samples.len() as i32,
+        n_processors as i32,
+      )
+    };
+    check_full_status(status, params)
+  }
+}
+
+/// Lock a state from the JS thread, which must not block while a run on the threadpool holds it
+pub(crate) fn try_lock_run(lock: &Mutex<()>) -> Result<MutexGuard<'_, ()>> {
+  match lock.try_lock() {
+    Ok(guard) => Ok(guard),
+    Err(TryLockError::Poisoned(poisoned)) => Ok(poisoned.into_inner()),
+    Err(TryLockError::WouldBlock) => Err(Error::new(
+      Status::GenericFailure,
+      "The state is busy with another run, await it or use another state",
+    )),
+  }
+}
+
+pub(crate) fn check_full_status(status: i32, params: &WhisperFullParams) -> Result<()> {
+  if status != 0 {
+    if params.is_aborted() {
+      return Err(Error::new(Status::Cancelled, "Whisper full was aborted"));
+    }
+    return Err(Error::new(
+      Status::GenericFailure,
+      format!("Failed to run full whisper model: {status}"),
+    ));
+  }
+  Ok(())
+}
+
+impl Drop for WhisperContext {
   fn drop(&mut self) {
     unsafe {
       sys::whisper_free(self.inner);
@@ -46,6 +259,31 @@ impl Drop for Whisper {
   }
 }
 
+#[napi]
+pub struct Whisper {
+  ctx: Arc<WhisperContext>,
+  /// Own state of the instances created by `Whisper.shared`, the others run on the default state of the context
+  state: Option<Arc<WhisperState>>,
+  callback_user_data: *mut WhisperCallbackUserData,
+}
+
+pub(crate) fn model_source(model: &Either<&[u8], String>) -> Either<&[u8], &str> {
+  match model {
+    Either::A(buf) => Either::A(buf),
+    Either::B(filepath) => Either::B(filepath.as_str()),
+  }
+}
+
+impl Whisper {
+  pub(crate) fn from_context(ctx: Arc<WhisperContext>) -> Self {
+    Self {
+      ctx,
+      state: None,
+      callback_user_data: ptr::null_mut(),
+    }
+  }
+}
+
 #[napi]
 impl Whisper {
   #[napi]
@@ -85,126 +323,110 @@ impl Whisper {
   }
 
   #[napi(constructor)]
+  /// Load the model from a buffer or a file path, a path is read by whisper.cpp without an intermediate copy
   pub fn new(model: Either<&[u8], String>, params: Option<WhisperContextParams>) -> Result<Self> {
-    let model = match model {
-      Either::A(buf) => Cow::Borrowed(buf),
-      Either::B(filepath) => {
-        let mut file = File::open(filepath)?;
-        let mut buf = Vec::with_capacity(file.metadata()?.len() as usize);
-        file.read_to_end(&mut buf)?;
-        Cow::Owned(buf)
-      }
-    };
-    let inner = unsafe {
-      sys::whisper_init_from_buffer_with_params(
-        model.as_ptr().cast_mut().cast(),
-        model.len(),
-        params.map(Into::into).unwrap_or_default(),
-      )
-    };
-    if inner.is_null() {
-      return Err(Error::new(
-        Status::InvalidArg,
-        "Failed to initialize Whisper model from buffer",
-      ));
-    }
-
-    Ok(Self {
-      inner,
-      callback_user_data: ptr::null_mut(),
-    })
+    let model = model_source(&model);
+    let params = ContextParams::for_model(model, params)?;
+    Ok(Self::from_context(Arc::new(WhisperContext::load(
+      model, params,
+    )?)))
   }
 
   #[napi(getter)]
   /// mel length
-  pub fn get_n_len(&self) -> i32 {
-    unsafe { sys::whisper_n_len(self.inner) }
+  pub fn get_n_len(&self) -> Result<i32> {
+    let _guard = self.ctx.try_lock_state(self.state.as_deref())?;
+    Ok(match self.state.as_deref() {
+      Some(state) => unsafe { sys::whisper_n_len_from_state(state.inner) },
+      None => unsafe { sys::whisper_n_len(self.ctx.inner) },
+    })
   }
 
   #[napi(getter)]
   pub fn get_n_vocab(&self) -> i32 {
-    unsafe { sys::whisper_n_vocab(self.inner) }
+    unsafe { sys::whisper_n_vocab(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_n_text(&self) -> i32 {
-    unsafe { sys::whisper_n_text_ctx(self.inner) }
+    unsafe { sys::whisper_n_text_ctx(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_n_audio(&self) -> i32 {
-    unsafe { sys::whisper_n_audio_ctx(self.inner) }
+    unsafe { sys::whisper_n_audio_ctx(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_is_multilingual(&self) -> i32 {
-    unsafe { sys::whisper_is_multilingual(self.inner) }
+    unsafe { sys::whisper_is_multilingual(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_vocab(&self) -> i32 {
-    unsafe { sys::whisper_model_n_vocab(self.inner) }
+    unsafe { sys::whisper_model_n_vocab(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_audio_ctx(&self) -> i32 {
-    unsafe { sys::whisper_model_n_audio_ctx(self.inner) }
+    unsafe { sys::whisper_model_n_audio_ctx(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_audio_state(&self) -> i32 {
-    unsafe { sys::whisper_model_n_audio_state(self.inner) }
+    unsafe { sys::whisper_model_n_audio_state(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_audio_head(&self) -> i32 {
-    unsafe { sys::whisper_model_n_audio_head(self.inner) }
+    unsafe { sys::whisper_model_n_audio_head(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_audio_layer(&self) -> i32 {
-    unsafe { sys::whisper_model_n_audio_layer(self.inner) }
+    unsafe { sys::whisper_model_n_audio_layer(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_text_ctx(&self) -> i32 {
-    unsafe { sys::whisper_model_n_text_ctx(self.inner) }
+    unsafe { sys::whisper_model_n_text_ctx(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_text_state(&self) -> i32 {
-    unsafe { sys::whisper_model_n_text_state(self.inner) }
+    unsafe { sys::whisper_model_n_text_state(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_text_head(&self) -> i32 {
-    unsafe { sys::whisper_model_n_text_head(self.inner) }
+    unsafe { sys::whisper_model_n_text_head(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_text_layer(&self) -> i32 {
-    unsafe { sys::whisper_model_n_text_layer(self.inner) }
+    unsafe { sys::whisper_model_n_text_layer(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_n_mels(&self) -> i32 {
-    unsafe { sys::whisper_model_n_mels(self.inner) }
+    unsafe { sys::whisper_model_n_mels(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_ftype(&self) -> i32 {
-    unsafe { sys::whisper_model_ftype(self.inner) }
+    unsafe { sys::whisper_model_ftype(self.ctx.inner) }
   }
 
   #[napi(getter)]
   pub fn get_model_type(&self) -> i32 {
-    unsafe { sys::whisper_model_type(self.inner) }
+    unsafe { sys::whisper_model_type(self.ctx.inner) }
   }
 
   #[napi(getter)]
-  /// Language id associated with the context's default state
-  pub fn get_full_lang_id(&self) -> i32 {
-    unsafe { sys::whisper_full_lang_id(self.inner) }
+  /// Language id of the last run, on the context's default state or the own state of a shared instance
+  pub fn get_full_lang_id(&self) -> Result<i32> {
+    let _guard = self.ctx.try_lock_state(self.state.as_deref())?;
+    Ok(self.ctx.result_source(self.state.as_deref()).lang_id())
   }
 
   #[napi(getter)]
@@ -221,45 +443,302 @@ impl Whisper {
     if let Ok(state) = this.get_named_property_unchecked(STATE_PROPERTY_KEY) {
       return Some(state);
     }
-    let whisper_state = WhisperState { inner: state_ptr }.into_instance(env).ok()?;
+    let whisper_state = WhisperState::borrowed(self.ctx.inner, state_ptr)
+      .into_instance(env)
+      .ok()?;
     whisper_state
       .assign_to_this_with_attributes(STATE_PROPERTY_KEY, PropertyAttributes::Default, &mut this)
       .ok()
   }
 
   #[napi]
-  /// Return the number of tokens in the provided text
-  pub fn count(&self) -> u32 {
-    unsafe { sys::whisper_token_count(self.inner) as u32 }
+  pub fn full(&mut self, parmas: &mut WhisperFullParams, samples: &[f32]) -> Result<String> {
+    let _guard = self.ctx.try_lock_state(self.state.as_deref())?;
+    let _run = parmas.begin_run(None)?;
+    self.callback_user_data = parmas.callback_user_data;
+    let result = self.ctx.full(self.state.as_deref(), parmas, samples);
+    self.callback_user_data = ptr::null_mut();
+    result?;
+    Ok(self.ctx.result_source(self.state.as_deref()).text())
+  }
+
+  #[napi]
+  /// Run the whisper model on the libuv threadpool, the callbacks on `params` keep firing while it runs.
+  /// Aborting the `signal` stops the decoding through the abort callback.
+  pub fn full_async(
+    &self,
+    params: Reference<WhisperFullParams>,
+    samples: Float32Array,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<FullTask> {
+    let aborted = watch_abort_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      FullTask {
+        ctx: self.ctx.clone(),
+        state: self.state.clone(),
+        params,
+        aborted,
+        samples,
+      },
+      signal,
+    )
   }
 
   #[napi]
-  pub fn full(&mut self, parmas: &mut WhisperFullParams, samples: &[f32]) -> Result<String> {
-    self.callback_user_data = parmas.callback_user_data;
-    let status = unsafe {
-      sys::whisper_full(
-        self.inner,
-        parmas.inner.clone(),
-        samples.as_ptr().cast(),
-        samples.len() as i32,
-      )
-    };
-    if status != 0 {
-      return Err(Error::new(
-        Status::GenericFailure,
-        format!("Failed to run full whisper model: {status}"),
-      ));
+  /// Create a new state on the loaded model, each state can run `fullWithState` concurrently
+  pub fn create_state(&self) -> Result<WhisperState> {
+    WhisperState::new(self.ctx.clone())
+  }
+
+  #[napi]
+  /// Run the whisper model on the provided state instead of the default state of the context
+  pub fn full_with_state(
+    &self,
+    state: &WhisperState,
+    params: &mut WhisperFullParams,
+    samples: &[f32],
+  ) -> Result<String> {
+    self.assert_own_state(state)?;
+    let _guard = state.try_lock()?;
+    let _run = params.begin_run(None)?;
+    self.ctx.full(Some(state), params, samples)?;
+    Ok(state.result_source().text())
+  }
+
+  #[napi]
+  /// Async version of `fullWithState`, running on the libuv threadpool
+  pub fn full_with_state_async(
+    &self,
+    state: Reference<WhisperState>,
+    params: Reference<WhisperFullParams>,
+    samples: Float32Array,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<FullWithStateTask>> {
+    self.assert_own_state(&state)?;
+    let aborted = watch_abort_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      FullWithStateTask {
+        ctx: self.ctx.clone(),
+        state,
+        params,
+        aborted,
+        samples,
+      },
+      signal,
+    ))
+  }
+
+  fn assert_own_state(&self, state: &WhisperState) -> Result<()> {
+    match &state.owner {
+      Some(owner) if Arc::ptr_eq(owner, &self.ctx) => Ok(()),
+      _ => Err(Error::new(
+        Status::InvalidArg,
+        "The state was not created by `createState` of this Whisper instance",
+      )),
     }
+  }
+
+  #[napi]
+  /// Run the whisper model and return the segments with their timestamps instead of the joined text
+  pub fn transcribe(
+    &mut self,
+    params: &mut WhisperFullParams,
+    samples: &[f32],
+  ) -> Result<WhisperFullResult> {
+    let _guard = self.ctx.try_lock_state(self.state.as_deref())?;
+    let _run = params.begin_run(None)?;
+    self.callback_user_data = params.callback_user_data;
+    let result = self.ctx.full(self.state.as_deref(), params, samples);
     self.callback_user_data = ptr::null_mut();
-    let segments_length = unsafe { sys::whisper_full_n_segments(self.inner) };
-    let mut output = String::with_capacity(1024);
-    for i in 0..segments_length {
-      let segment = unsafe { sys::whisper_full_get_segment_text(self.inner, i) };
-      let text = unsafe { std::ffi::CStr::from_ptr(segment) };
-      if let Ok(s) = text.to_str() {
-        output.push_str(s);
-      }
-    }
+    result?;
+    let mut result = self.ctx.result_source(self.state.as_deref()).full_result();
+    params.fill_segments(&mut result.segments);
+    Ok(result)
+  }
+
+  #[napi]
+  /// Async version of `transcribe`, running on the libuv threadpool
+  pub fn transcribe_async(
+    &self,
+    params: Reference<WhisperFullParams>,
+    samples: Float32Array,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<TranscribeTask> {
+    let aborted = watch_abort_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      TranscribeTask {
+        ctx: self.ctx.clone(),
+        state: self.state.clone(),
+        params,
+        aborted,
+        samples,
+      },
+      signal,
+    )
+  }
+
+  #[napi]
+  /// Split the audio into `nProcessors` chunks and run them in parallel with `whisper_full_parallel`.
+  /// The segments of all chunks are merged with timestamps relative to the whole audio.
+  /// Always runs on the default state of the context, shared instances take turns on it.
+  pub fn transcribe_parallel(
+    &mut self,
+    params: &mut WhisperFullParams,
+    samples: &[f32],
+    n_processors: u32,
+  ) -> Result<WhisperFullResult> {
+    let _guard = self.ctx.try_lock_state(None)?;
+    let _run = params.begin_run(None)?;
+    self.callback_user_data = params.callback_user_data;
+    let result = self.ctx.full_parallel(params, samples, n_processors);
+    self.callback_user_data = ptr::null_mut();
+    result?;
+    let mut result = ResultSource::Context(self.ctx.inner).full_result();
+    params.fill_segments(&mut result.segments);
+    Ok(result)
+  }
+
+  #[napi]
+  /// Async version of `transcribeParallel`, running on the libuv threadpool
+  pub fn transcribe_parallel_async(
+    &self,
+    params: Reference<WhisperFullParams>,
+    samples: Float32Array,
+    n_processors: u32,
+    signal: Option<AbortSignal>,
+  ) -> AsyncTask<TranscribeParallelTask> {
+    let aborted = watch_abort_signal(signal.as_ref());
+    AsyncTask::with_optional_signal(
+      TranscribeParallelTask {
+        ctx: self.ctx.clone(),
+        params,
+        aborted,
+        samples,
+        n_processors,
+      },
+      signal,
+    )
+  }
+}
+
+/// Abort flag of a single run, the task installs it on the params with `begin_run` once it starts
+pub(crate) fn watch_abort_signal(signal: Option<&AbortSignal>) -> Arc<AtomicBool> {
+  let aborted = Arc::new(AtomicBool::new(false));
+  if let Some(signal) = signal {
+    let aborted = aborted.clone();
+    signal.on_abort(move || {
+      aborted.store(true, Ordering::Relaxed);
+    });
+  }
+  aborted
+}
+
+pub struct FullTask {
+  ctx: Arc<WhisperContext>,
+  state: Option<Arc<WhisperState>>,
+  params: Reference<WhisperFullParams>,
+  aborted: Arc<AtomicBool>,
+  samples: Float32Array,
+}
+
+#[napi]
+impl Task for FullTask {
+  type Output = String;
+  type JsValue = String;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let state = self.state.as_deref();
+    let _guard = self.ctx.lock_state(state);
+    let _run = self.params.begin_run(Some(self.aborted.clone()))?;
+    self.ctx.full(state, &self.params, &self.samples)?;
+    Ok(self.ctx.result_source(state).text())
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+pub struct FullWithStateTask {
+  ctx: Arc<WhisperContext>,
+  state: Reference<WhisperState>,
+  params: Reference<WhisperFullParams>,
+  aborted: Arc<AtomicBool>,
+  samples: Float32Array,
+}
+
+#[napi]
+impl Task for FullWithStateTask {
+  type Output = String;
+  type JsValue = String;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let _guard = self.state.lock();
+    let _run = self.params.begin_run(Some(self.aborted.clone()))?;
+    self
+      .ctx
+      .full(Some(&self.state), &self.params, &self.samples)?;
+    Ok(self.state.result_source().text())
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+pub struct TranscribeTask {
+  ctx: Arc<WhisperContext>,
+  state: Option<Arc<WhisperState>>,
+  params: Reference<WhisperFullParams>,
+  aborted: Arc<AtomicBool>,
+  samples: Float32Array,
+}
+
+#[napi]
+impl Task for TranscribeTask {
+  type Output = WhisperFullResult;
+  type JsValue = WhisperFullResult;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let state = self.state.as_deref();
+    let _guard = self.ctx.lock_state(state);
+    let _run = self.params.begin_run(Some(self.aborted.clone()))?;
+    self.ctx.full(state, &self.params, &self.samples)?;
+    let mut result = self.ctx.result_source(state).full_result();
+    self.params.fill_segments(&mut result.segments);
+    Ok(result)
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+pub struct TranscribeParallelTask {
+  ctx: Arc<WhisperContext>,
+  params: Reference<WhisperFullParams>,
+  aborted: Arc<AtomicBool>,
+  samples: Float32Array,
+  n_processors: u32,
+}
+
+#[napi]
+impl Task for TranscribeParallelTask {
+  type Output = WhisperFullResult;
+  type JsValue = WhisperFullResult;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let _guard = self.ctx.lock_default_state();
+    let _run = self.params.begin_run(Some(self.aborted.clone()))?;
+    self
+      .ctx
+      .full_parallel(&self.params, &self.samples, self.n_processors)?;
+    let mut result = ResultSource::Context(self.ctx.inner).full_result();
+    self.params.fill_segments(&mut result.segments);
+    Ok(result)
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
     Ok(output)
   }
 }
@@ -291,6 +770,9 @@ impl From<sys::ggml_log_level::GgmlLogLevel> for WhisperLogLevel {
 type LoggerCallback =
   ThreadsafeFunction<(WhisperLogLevel, String), (), (WhisperLogLevel, String), false, true>;
 
+/// The logger set by `setupLogger`, also used for the logs of the Rust side
+static LOGGER: AtomicPtr<LoggerCallback> = AtomicPtr::new(ptr::null_mut());
+
 #[napi]
 pub fn setup_logger(callback: Function<(WhisperLogLevel, String), ()>) -> Result<()> {
   let logger = callback
@@ -298,29 +780,31 @@ pub fn setup_logger(callback: Function<(WhisperLogLevel, String), ()>) -> Result
     .callee_handled::<false>()
     .weak::<true>()
     .build_callback(|ctx| Ok((ctx.value.0, ctx.value.1)))?;
-  unsafe {
-    sys::whisper_log_set(
-      Some(whisper_logger_callback),
-      Box::into_raw(Box::new(logger)).cast(),
-    )
-  };
+  // the previous logger may still be in use by a running model, so it is leaked
+  LOGGER.store(Box::into_raw(Box::new(logger)), Ordering::Release);
+  unsafe { sys::whisper_log_set(Some(whisper_logger_callback), ptr::null_mut()) };
   Ok(())
 }
 
+pub(crate) fn log(level: WhisperLogLevel, message: &str) {
+  let logger = LOGGER.load(Ordering::Acquire);
+  if logger.is_null() {
+    return;
+  }
+  let logger = unsafe { &*logger };
+  logger.call(
+    (level, message.trim().to_string()),
+    ThreadsafeFunctionCallMode::NonBlocking,
+  );
+}
+
 extern "C" fn whisper_logger_callback(
   level: sys::ggml_log_level::GgmlLogLevel,
   message: *const std::ffi::c_char,
-  user_data: *mut std::ffi::c_void,
+  _user_data: *mut std::ffi::c_void,
 ) {
-  if user_data.is_null() {
-    return;
-  }
-  let logger = unsafe { Box::from_raw(user_data.cast::<LoggerCallback>()) };
   let message = unsafe { std::ffi::CStr::from_ptr(message) };
   if let Ok(s) = message.to_str() {
-    logger.call(
-      (level.into(), s.trim().to_string()),
-      ThreadsafeFunctionCallMode::NonBlocking,
-    );
+    log(level.into(), s);
   }
 }
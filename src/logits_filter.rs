@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+
+use napi_derive::napi;
+
+use crate::sys::whisper_full_params;
+
+#[napi(object)]
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBias {
+  pub token: i32,
+  /// Added to the logit of the token, positive values make the token more likely
+  pub bias: f64,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+pub struct HotWords {
+  /// Tokens of every word, e.g. from `Whisper.tokenizeHotWords`
+  pub words: Vec<Vec<i32>>,
+  /// Added to the logits of the next token of a word once its first tokens are decoded
+  pub bias: f64,
+  /// Added to the logits of the first token of every word, 0 by default.
+  /// The first token is often a common word piece, a large value boosts every word starting with it.
+  pub start_bias: Option<f64>,
+}
+
+#[derive(Default)]
+/// Token sequences of the hot words, node 0 is the root
+struct HotWordTrie {
+  children: Vec<HashMap<i32, usize>>,
+  /// Number of tokens of the longest word
+  depth: usize,
+}
+
+impl HotWordTrie {
+  fn new(words: &[Vec<i32>]) -> Self {
+    let mut children = vec![HashMap::new()];
+    for word in words {
+      let mut node = 0;
+      for token in word {
+        node = match children[node].get(token).copied() {
+          Some(next) => next,
+          None => {
+            children.push(HashMap::new());
+            let next = children.len() - 1;
+            children[node].insert(*token, next);
+            next
+          }
+        };
+      }
+    }
+    Self {
+      children,
+      depth: words.iter().map(Vec::len).max().unwrap_or(0),
+    }
+  }
+
+  fn find(&self, tokens: &[i32]) -> Option<usize> {
+    tokens
+      .iter()
+      .try_fold(0, |node, token| self.children[node].get(token).copied())
+  }
+
+  fn starts(&self) -> impl Iterator<Item = &i32> {
+    self.children.first().into_iter().flat_map(HashMap::keys)
+  }
+
+  /// Next tokens of the words whose first tokens end the decoded `tokens`
+  fn continuations(&self, tokens: &[i32]) -> Vec<i32> {
+    let mut next = Vec::new();
+    // only the last tokens shorter than the longest word can be the start of one
+    for n in 1..self.depth.min(tokens.len() + 1) {
+      if let Some(node) = self.find(&tokens[tokens.len() - n..]) {
+        next.extend(self.children[node].keys());
+      }
+    }
+    next.sort_unstable();
+    next.dedup();
+    next
+  }
+}
+
+#[derive(Default)]
+/// Native logits filter, run by whisper.cpp before every sampled token
+pub(crate) struct LogitsFilter {
+  pub(crate) token_bias: Vec<TokenBias>,
+  pub(crate) suppress_tokens: Vec<i32>,
+  pub(crate) hot_words: Option<HotWords>,
+  hot_word_trie: HotWordTrie,
+  pub(crate) fallback: FallbackTracker,
+}
+
+impl LogitsFilter {
+  pub(crate) fn set_hot_words(&mut self, hot_words: Option<HotWords>) {
+    self.hot_word_trie = match &hot_words {
+      Some(hot_words) => HotWordTrie::new(&hot_words.words),
+      None => HotWordTrie::default(),
+    };
+    self.hot_words = hot_words;
+  }
+
+  /// `tokens` are the tokens decoded so far by the decoder the logits are sampled for
+  pub(crate) fn apply(&self, tokens: &[i32], logits: &mut [f32]) {
+    fn add(logits: &mut [f32], token: i32, bias: f64) {
+      if let Some(logit) = logits.get_mut(token as usize) {
+        *logit += bias as f32;
+      }
+    }
+    for TokenBias { token, bias } in &self.token_bias {
+      add(logits, *token, *bias);
+    }
+    if let Some(hot_words) = &self.hot_words {
+      let start_bias = hot_words.start_bias.unwrap_or(0.0);
+      if start_bias != 0.0 {
+        for token in self.hot_word_trie.starts() {
+          add(logits, *token, start_bias);
+        }
+      }
+      for token in self.hot_word_trie.continuations(tokens) {
+        add(logits, token, hot_words.bias);
+      }
+    }
+    for token in &self.suppress_tokens {
+      if let Some(logit) = logits.get_mut(*token as usize) {
+        *logit = f32::NEG_INFINITY;
+      }
+    }
+  }
+}
+
+#[derive(Default)]
+/// Tracks the temperature fallbacks of `whisper_full`, which are not exposed by whisper.cpp.
+/// Every window starts with the encoder begin callback, every decoding attempt of the window
+/// starts with empty decoder sequences in the logits filter.
+pub(crate) struct FallbackTracker {
+  enabled: bool,
+  temperatures: Vec<f32>,
+  /// Index of the first segment and number of decoding attempts of every window
+  windows: Vec<(i32, usize)>,
+  first_step: bool,
+}
+
+impl FallbackTracker {
+  /// Reset before a run, `whisper_full_parallel` runs the windows of several states concurrently so it is not tracked
+  pub(crate) fn reset(&mut self, params: &whisper_full_params, enabled: bool) {
+    // same as `whisper_full_with_state`
+    let mut temperatures = Vec::new();
+    if params.temperature_inc > 0.0 {
+      let mut t = params.temperature;
+      while t < 1.0 + 1e-6 {
+        temperatures.push(t);
+        t += params.temperature_inc;
+      }
+    } else {
+      temperatures.push(params.temperature);
+    }
+    self.enabled = enabled;
+    self.temperatures = temperatures;
+    self.windows.clear();
+    self.first_step = false;
+  }
+
+  pub(crate) fn begin_window(&mut self, first_segment: i32) {
+    self.windows.push((first_segment, 0));
+    self.first_step = false;
+  }
+
+  pub(crate) fn decode_step(&mut self, n_tokens: i32) {
+    // all decoders of an attempt start from an empty sequence
+    if n_tokens == 0 {
+      if !self.first_step {
+        if let Some((_, attempts)) = self.windows.last_mut() {
+          *attempts += 1;
+        }
+      }
+      self.first_step = true;
+    } else {
+      self.first_step = false;
+    }
+  }
+
+  /// Temperature of the last decoding attempt of the window of the segment, an estimate since
+  /// an attempt is only recognized by its decoders starting from empty sequences
+  pub(crate) fn temperature(&self, i_segment: i32) -> Option<f64> {
+    if !self.enabled {
+      return None;
+    }
+    let (_, attempts) = self
+      .windows
+      .iter()
+      .rev()
+      .find(|(first_segment, _)| *first_segment <= i_segment)?;
+    let i = attempts
+      .saturating_sub(1)
+      .min(self.temperatures.len().saturating_sub(1));
+    self.temperatures.get(i).map(|t| *t as f64)
+  }
+}
@@ -0,0 +1,291 @@
+// Chunked transcription of recordings longer than a single `whisper_full` run is comfortable with
+
+use std::{
+  ops::Range,
+  ptr,
+  sync::{atomic::AtomicBool, Arc},
+};
+
+use napi::{bindgen_prelude::*, Task};
+use napi_derive::napi;
+
+use crate::{
+  diarization::alternate_speakers,
+  full_params::{Segment, WhisperFullParams},
+  result::WhisperFullResult,
+  sys, watch_abort_signal, Whisper, WhisperContext, WhisperState, WHISPER_SAMPLE_RATE,
+};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct WhisperLongOptions {
+  /// Length of the chunks in milliseconds, 300000 (5 minutes) by default
+  pub chunk_ms: Option<u32>,
+  /// Audio shared by consecutive chunks in milliseconds, 5000 by default
+  pub overlap_ms: Option<u32>,
+  /// Use the text before the chunk as prompt, after `promptTokens` or `initialPrompt` of the params, true by default
+  pub keep_context: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct LongOptions {
+  n_samples_chunk: usize,
+  n_samples_overlap: usize,
+  keep_context: bool,
+}
+
+impl LongOptions {
+  fn new(options: Option<WhisperLongOptions>) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let chunk_ms = options.chunk_ms.unwrap_or(300_000);
+    let overlap_ms = options.overlap_ms.unwrap_or(5000);
+    if chunk_ms < 1000 || overlap_ms as u64 * 2 >= chunk_ms as u64 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "chunkMs must be at least 1000 and more than twice overlapMs",
+      ));
+    }
+    Ok(Self {
+      n_samples_chunk: ms_samples(chunk_ms),
+      n_samples_overlap: ms_samples(overlap_ms),
+      keep_context: options.keep_context.unwrap_or(true),
+    })
+  }
+}
+
+fn ms_samples(ms: u32) -> usize {
+  (ms as u64 * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+fn samples_ms(n_samples: usize) -> u32 {
+  (n_samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64) as u32
+}
+
+/// Every chunk starts `overlap` before the end of the previous one
+fn chunks(n_samples: usize, options: &LongOptions) -> Vec<Range<usize>> {
+  let mut chunks = Vec::new();
+  let mut start = 0;
+  loop {
+    let end = (start + options.n_samples_chunk).min(n_samples);
+    chunks.push(start..end);
+    if end == n_samples {
+      return chunks;
+    }
+    start = end - options.n_samples_overlap;
+  }
+}
+
+/// Only letters and digits in lower case, the same sentence decoded twice may differ in punctuation
+fn normalized(text: &str) -> String {
+  text
+    .chars()
+    .filter(|c| c.is_alphanumeric())
+    .flat_map(char::to_lowercase)
+    .collect()
+}
+
+/// `initial` followed by the text tokens of the last segments ending in `[start, end]`,
+/// at most as many as whisper.cpp keeps from a prompt
+fn prompt_before(
+  ctx: &WhisperContext,
+  initial: &[i32],
+  segments: &[Segment],
+  start: u32,
+  end: u32,
+) -> Vec<i32> {
+  let eot = unsafe { sys::whisper_token_eot(ctx.inner) };
+  let n_max = (unsafe { sys::whisper_n_text_ctx(ctx.inner) } / 2).max(0) as usize;
+  let mut tokens = segments
+    .iter()
+    .rev()
+    .take_while(|segment| segment.end >= start)
+    .filter(|segment| segment.end <= end)
+    .flat_map(|segment| segment.tokens.iter().rev())
+    .filter(|token| token.id < eot)
+    .map(|token| token.id)
+    .take(n_max.saturating_sub(initial.len()))
+    .collect::<Vec<_>>();
+  tokens.extend(initial.iter().rev());
+  tokens.reverse();
+  tokens
+}
+
+fn run_chunks(
+  ctx: &WhisperContext,
+  state: Option<&WhisperState>,
+  params: &WhisperFullParams,
+  samples: &[f32],
+  options: LongOptions,
+) -> Result<WhisperFullResult> {
+  let chunks = chunks(samples.len(), &options);
+  let n_samples = samples.len().max(1) as f64;
+  let overlap_ms = samples_ms(options.n_samples_overlap);
+  let mut output: Option<WhisperFullResult> = None;
+  let mut segments: Vec<Segment> = Vec::new();
+  // the prompt of the params would be replaced by the text of the previous chunks
+  let initial_prompt = if options.keep_context {
+    params.prompt(ctx)?
+  } else {
+    Vec::new()
+  };
+  let mut prompt_tokens = Vec::new();
+  for (i, chunk) in chunks.iter().enumerate() {
+    if params.is_aborted() {
+      return Err(Error::new(Status::Cancelled, "Whisper full was aborted"));
+    }
+    // every chunk keeps the segments centered in its part of the audio, split in the middle of the overlaps
+    let own_start = if i == 0 {
+      0
+    } else {
+      samples_ms(chunk.start) + overlap_ms / 2
+    };
+    let own_end = match chunks.get(i + 1) {
+      Some(next) => samples_ms(next.start) + overlap_ms / 2,
+      None => u32::MAX,
+    };
+
+    params.set_time_offset(samples_ms(chunk.start));
+    params.set_progress_range(Some((
+      chunk.start as f64 * 100.0 / n_samples,
+      chunk.end as f64 * 100.0 / n_samples,
+    )));
+    ctx.full_with_prompt(
+      state,
+      params,
+      &samples[chunk.clone()],
+      (options.keep_context && !prompt_tokens.is_empty()).then_some(&prompt_tokens[..]),
+    )?;
+    let mut result = ctx.result_source(state).full_result();
+    params.fill_segments(&mut result.segments);
+
+    for mut segment in result.segments.drain(..) {
+      let center = ((segment.start as u64 + segment.end as u64) / 2) as u32;
+      if center < own_start || center >= own_end {
+        continue;
+      }
+      if let Some(last) = segments.last() {
+        // the same sentence decoded by both chunks with slightly different timestamps
+        if normalized(&last.text) == normalized(&segment.text)
+          && segment.start <= last.end + overlap_ms
+        {
+          continue;
+        }
+        segment.start = segment.start.max(last.end);
+        segment.end = segment.end.max(segment.start);
+      }
+      segment.index = segments.len() as u32;
+      params.new_segment(segment.clone());
+      segments.push(segment);
+    }
+    if let Some(next) = chunks.get(i + 1) {
+      // only the text of this chunk, a chunk without segments must not prompt the next one with older text
+      prompt_tokens = prompt_before(
+        ctx,
+        &initial_prompt,
+        &segments,
+        samples_ms(chunk.start),
+        samples_ms(next.start),
+      );
+    }
+    output.get_or_insert(result);
+  }
+  // the speakers of every chunk start over
+  if params.inner.tdrz_enable {
+    alternate_speakers(&mut segments);
+  }
+  let mut output = output.unwrap_or(WhisperFullResult {
+    lang_id: -1,
+    language: None,
+    segments: Vec::new(),
+  });
+  output.segments = segments;
+  Ok(output)
+}
+
+fn transcribe_chunks(
+  ctx: &WhisperContext,
+  state: Option<&WhisperState>,
+  params: &WhisperFullParams,
+  samples: &[f32],
+  options: LongOptions,
+) -> Result<WhisperFullResult> {
+  params.hold_new_segments(true);
+  let result = run_chunks(ctx, state, params, samples, options);
+  params.hold_new_segments(false);
+  params.set_time_offset(0);
+  params.set_progress_range(None);
+  result
+}
+
+#[napi]
+impl Whisper {
+  #[napi]
+  /// Transcribe audio of any length in overlapping chunks, each one prompted with the text before it.
+  /// The segments of the overlaps are deduplicated and the timestamps refer to the whole audio,
+  /// `onProgress` reports the progress of the whole audio and `onNewSegment` fires for the deduplicated segments once their chunk is done.
+  /// The language of the result is the one of the first chunk.
+  pub fn transcribe_long(
+    &mut self,
+    params: &mut WhisperFullParams,
+    samples: &[f32],
+    options: Option<WhisperLongOptions>,
+  ) -> Result<WhisperFullResult> {
+    let options = LongOptions::new(options)?;
+    let _guard = self.ctx.try_lock_state(self.state.as_deref())?;
+    let _run = params.begin_run(None)?;
+    self.callback_user_data = params.callback_user_data;
+    let result = transcribe_chunks(&self.ctx, self.state.as_deref(), params, samples, options);
+    self.callback_user_data = ptr::null_mut();
+    result
+  }
+
+  #[napi]
+  /// Async version of `transcribeLong`, running on the libuv threadpool
+  pub fn transcribe_long_async(
+    &self,
+    params: Reference<WhisperFullParams>,
+    samples: Float32Array,
+    options: Option<WhisperLongOptions>,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<TranscribeLongTask>> {
+    let options = LongOptions::new(options)?;
+    let aborted = watch_abort_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      TranscribeLongTask {
+        ctx: self.ctx.clone(),
+        state: self.state.clone(),
+        params,
+        aborted,
+        samples,
+        options,
+      },
+      signal,
+    ))
+  }
+}
+
+pub struct TranscribeLongTask {
+  ctx: Arc<WhisperContext>,
+  state: Option<Arc<WhisperState>>,
+  params: Reference<WhisperFullParams>,
+  aborted: Arc<AtomicBool>,
+  samples: Float32Array,
+  options: LongOptions,
+}
+
+#[napi]
+impl Task for TranscribeLongTask {
+  type Output = WhisperFullResult;
+  type JsValue = WhisperFullResult;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let state = self.state.as_deref();
+    let _guard = self.ctx.lock_state(state);
+    let _run = self.params.begin_run(Some(self.aborted.clone()))?;
+    transcribe_chunks(&self.ctx, state, &self.params, &self.samples, self.options)
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
@@ -0,0 +1,152 @@
+use std::{
+  collections::HashMap,
+  fs,
+  path::PathBuf,
+  sync::{Arc, Mutex, MutexGuard, OnceLock, TryLockError, Weak},
+  time::SystemTime,
+};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{
+  context_params::{ContextParams, WhisperContextParams},
+  model_source, Whisper, WhisperContext, WhisperState,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ModelSource {
+  /// A replaced file at the same path is a different model
+  File {
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+  },
+  /// Named by the caller, the bytes of a buffer are not compared
+  Key(String),
+}
+
+/// The model and every resolved `whisper_context_params` field, contexts loaded with different params are not shared
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct ModelKey {
+  source: ModelSource,
+  use_gpu: bool,
+  flash_attn: bool,
+  gpu_device: i32,
+  dtw_token_timestamps: bool,
+  dtw_aheads_preset: u32,
+  dtw_n_top: i32,
+  dtw_aheads: Vec<(i32, i32)>,
+  dtw_mem_size: usize,
+}
+
+impl ModelKey {
+  fn new(model: Either<&[u8], &str>, key: Option<String>, params: &ContextParams) -> Result<Self> {
+    let source = match (key, model) {
+      (Some(key), _) => ModelSource::Key(key),
+      (None, Either::A(_)) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "A key naming the model is required to share a model passed as a buffer",
+        ))
+      }
+      (None, Either::B(filepath)) => {
+        let path = fs::canonicalize(filepath)?;
+        let metadata = fs::metadata(&path)?;
+        ModelSource::File {
+          path,
+          len: metadata.len(),
+          modified: metadata.modified().ok(),
+        }
+      }
+    };
+    let inner = &params.inner;
+    Ok(Self {
+      source,
+      use_gpu: inner.use_gpu,
+      flash_attn: inner.flash_attn,
+      gpu_device: inner.gpu_device,
+      dtw_token_timestamps: inner.dtw_token_timestamps,
+      dtw_aheads_preset: inner.dtw_aheads_preset,
+      dtw_n_top: inner.dtw_n_top,
+      dtw_aheads: params
+        .aheads
+        .iter()
+        .map(|head| (head.n_text_layer, head.n_head))
+        .collect(),
+      dtw_mem_size: inner.dtw_mem_size,
+    })
+  }
+}
+
+/// Locked while the model is loading, so concurrent loads of the same model wait for the first one
+type ModelSlot = Arc<Mutex<Weak<WhisperContext>>>;
+
+/// Process-wide, so `worker_threads` share the contexts too.
+/// Only weak references are kept, the weights are freed with the last `Whisper` or `WhisperState` using them.
+static MODELS: OnceLock<Mutex<HashMap<ModelKey, ModelSlot>>> = OnceLock::new();
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn models() -> MutexGuard<'static, HashMap<ModelKey, ModelSlot>> {
+  let mut models = lock(MODELS.get_or_init(Default::default));
+  // a slot only referenced by the map is not loading, so locking it does not block
+  models.retain(|_, slot| Arc::strong_count(slot) > 1 || lock(slot).strong_count() > 0);
+  models
+}
+
+fn shared_context(
+  model: Either<&[u8], &str>,
+  key: Option<String>,
+  params: Option<WhisperContextParams>,
+) -> Result<Arc<WhisperContext>> {
+  let params = ContextParams::for_model(model, params)?;
+  let key = ModelKey::new(model, key, &params)?;
+  let slot = models().entry(key).or_default().clone();
+  let mut cached = lock(&slot);
+  if let Some(ctx) = cached.upgrade() {
+    return Ok(ctx);
+  }
+  let ctx = Arc::new(WhisperContext::load(model, params)?);
+  *cached = Arc::downgrade(&ctx);
+  Ok(ctx)
+}
+
+#[napi]
+impl Whisper {
+  #[napi(factory)]
+  /// Same as the constructor, but reuses the context of a live `Whisper` loaded from the same file with the same params,
+  /// in this thread or any `worker_threads`. The weights are freed when the last instance sharing them is garbage collected.
+  /// `key` names the model instead of its path and is required for a buffer, instances with the same key share the model.
+  /// Every shared instance runs on its own state, so they transcribe concurrently and only share the weights.
+  pub fn shared(
+    model: Either<&[u8], String>,
+    params: Option<WhisperContextParams>,
+    key: Option<String>,
+  ) -> Result<Self> {
+    let ctx = shared_context(model_source(&model), key, params)?;
+    let state = WhisperState::new(ctx.clone())?;
+    Ok(Self {
+      state: Some(Arc::new(state)),
+      ..Self::from_context(ctx)
+    })
+  }
+
+  #[napi]
+  /// Number of models currently loaded through `Whisper.shared` in the process
+  pub fn shared_model_count() -> u32 {
+    models()
+      .values()
+      .filter(|slot| match slot.try_lock() {
+        Ok(ctx) => ctx.strong_count() > 0,
+        Err(TryLockError::Poisoned(poisoned)) => poisoned.into_inner().strong_count() > 0,
+        // still loading
+        Err(TryLockError::WouldBlock) => false,
+      })
+      .count() as u32
+  }
+}
@@ -0,0 +1,325 @@
+use std::{
+  fs::File,
+  io::{self, BufReader, Read},
+};
+
+use napi::{bindgen_prelude::*, Task};
+use napi_derive::napi;
+use sha1::{Digest, Sha1};
+
+use crate::{context_params::WhisperAlignmentHeadsPreset, sys};
+
+/// Magic, the 10 hyper parameters and the ftype
+pub(crate) const MODEL_HEADER_SIZE: usize = 4 * 12;
+
+/// Hyper parameters at the start of a whisper.cpp ggml model file, read without loading the model
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct ModelHeader {
+  pub(crate) n_vocab: i32,
+  pub(crate) n_audio_ctx: i32,
+  pub(crate) n_audio_state: i32,
+  pub(crate) n_audio_head: i32,
+  pub(crate) n_audio_layer: i32,
+  pub(crate) n_text_ctx: i32,
+  pub(crate) n_text_state: i32,
+  pub(crate) n_text_head: i32,
+  pub(crate) n_text_layer: i32,
+  pub(crate) n_mels: i32,
+  /// With the quantization version in the thousands
+  pub(crate) ftype: i32,
+}
+
+pub(crate) fn model_error(message: impl AsRef<str>) -> Error {
+  Error::new(
+    Status::InvalidArg,
+    format!("Invalid model file: {}", message.as_ref()),
+  )
+}
+
+pub(crate) fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+  let mut buf = [0u8; N];
+  reader
+    .read_exact(&mut buf)
+    .map_err(|err| model_error(format!("failed to read: {err}")))?;
+  Ok(buf)
+}
+
+pub(crate) fn read_i32(reader: &mut impl Read) -> Result<i32> {
+  read_bytes(reader).map(i32::from_le_bytes)
+}
+
+fn skip(reader: &mut impl Read, n: u64) -> Result<()> {
+  let skipped = io::copy(&mut reader.take(n), &mut io::sink())?;
+  if skipped != n {
+    return Err(model_error("unexpected end of file"));
+  }
+  Ok(())
+}
+
+impl ModelHeader {
+  pub(crate) fn read(mut reader: impl Read) -> Result<Self> {
+    let magic = u32::from_le_bytes(read_bytes(&mut reader)?);
+    if magic != sys::GGML_FILE_MAGIC {
+      return Err(model_error(format!("bad magic {magic:#010x}")));
+    }
+    Ok(Self {
+      n_vocab: read_i32(&mut reader)?,
+      n_audio_ctx: read_i32(&mut reader)?,
+      n_audio_state: read_i32(&mut reader)?,
+      n_audio_head: read_i32(&mut reader)?,
+      n_audio_layer: read_i32(&mut reader)?,
+      n_text_ctx: read_i32(&mut reader)?,
+      n_text_state: read_i32(&mut reader)?,
+      n_text_head: read_i32(&mut reader)?,
+      n_text_layer: read_i32(&mut reader)?,
+      n_mels: read_i32(&mut reader)?,
+      ftype: read_i32(&mut reader)?,
+    })
+  }
+
+  /// Same check as `whisper_is_multilingual`
+  pub(crate) fn is_multilingual(&self) -> bool {
+    self.n_vocab >= 51865
+  }
+
+  /// Same as `whisper_model_type_readable`
+  pub(crate) fn model_type(&self) -> &'static str {
+    match self.n_audio_layer {
+      4 => "tiny",
+      6 => "base",
+      12 => "small",
+      24 => "medium",
+      32 => "large",
+      _ => "unknown",
+    }
+  }
+
+  /// Alignment heads preset of the original OpenAI model with the same shape.
+  /// Fine-tuned models keep the shape but may have moved heads, distilled models have fewer text layers and no preset.
+  /// large-v1 and large-v2 share the shape, large-v2 is assumed.
+  pub(crate) fn alignment_heads_preset(&self) -> Option<WhisperAlignmentHeadsPreset> {
+    use WhisperAlignmentHeadsPreset::*;
+
+    if self.n_text_layer != self.n_audio_layer {
+      return None;
+    }
+    let multilingual = self.is_multilingual();
+    Some(match (self.n_audio_layer, multilingual) {
+      (4, false) => TinyEn,
+      (4, true) => Tiny,
+      (6, false) => BaseEn,
+      (6, true) => Base,
+      (12, false) => SmallEn,
+      (12, true) => Small,
+      (24, false) => MediumEn,
+      (24, true) => Medium,
+      (32, true) if self.n_mels == 128 => LargeV3,
+      (32, true) => LargeV2,
+      _ => return None,
+    })
+  }
+
+  /// Fallback for models without a preset, the last half of the text layers like the reference implementation
+  pub(crate) fn dtw_n_top(&self) -> i32 {
+    (self.n_text_layer / 2).max(1)
+  }
+}
+
+/// Block size and bytes per block of the ggml tensor types found in whisper.cpp models
+fn ggml_type_size(ttype: i32) -> Option<(u64, u64)> {
+  Some(match ttype {
+    // F32
+    0 => (1, 4),
+    // F16
+    1 => (1, 2),
+    // Q4_0
+    2 => (32, 18),
+    // Q4_1
+    3 => (32, 20),
+    // Q5_0
+    6 => (32, 22),
+    // Q5_1
+    7 => (32, 24),
+    // Q8_0
+    8 => (32, 34),
+    // Q2_K
+    10 => (256, 84),
+    // Q3_K
+    11 => (256, 110),
+    // Q4_K
+    12 => (256, 144),
+    // Q5_K
+    13 => (256, 176),
+    // Q6_K
+    14 => (256, 210),
+    // BF16
+    30 => (1, 2),
+    _ => return None,
+  })
+}
+
+/// Hashes everything read through it
+struct Sha1Reader<R> {
+  inner: R,
+  hasher: Sha1,
+}
+
+impl<R: Read> Read for Sha1Reader<R> {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    let n = self.inner.read(buf)?;
+    self.hasher.update(&buf[..n]);
+    Ok(n)
+  }
+}
+
+#[napi(object)]
+/// Hyper parameters and layout of a whisper.cpp model file, see `inspectModel`
+pub struct ModelInfo {
+  pub n_vocab: i32,
+  pub n_audio_ctx: i32,
+  pub n_audio_state: i32,
+  pub n_audio_head: i32,
+  pub n_audio_layer: i32,
+  pub n_text_ctx: i32,
+  pub n_text_state: i32,
+  pub n_text_head: i32,
+  pub n_text_layer: i32,
+  pub n_mels: i32,
+  /// Weight type of the model, e.g. 1 for F16, see `ggml_ftype`
+  pub ftype: i32,
+  pub qnt_version: i32,
+  /// e.g. "base", "unknown" for shapes not released by OpenAI
+  pub model_type: String,
+  pub is_multilingual: bool,
+  pub n_tensors: u32,
+  /// SHA-1 of the whole file in hex, the checksum listed for the models on huggingface.co/ggerganov/whisper.cpp
+  pub sha1: String,
+}
+
+/// Walk the whole model file without allocating the tensors, the layout follows `whisper_model_load`
+fn inspect(reader: impl Read) -> Result<ModelInfo> {
+  let mut reader = Sha1Reader {
+    inner: reader,
+    hasher: Sha1::new(),
+  };
+  let header = ModelHeader::read(&mut reader)?;
+
+  // mel filters
+  let n_mel = read_i32(&mut reader)?;
+  let n_fft = read_i32(&mut reader)?;
+  skip(&mut reader, 4 * n_mel.max(0) as u64 * n_fft.max(0) as u64)?;
+
+  // vocab
+  let n_vocab = read_i32(&mut reader)?;
+  for _ in 0..n_vocab.max(0) {
+    let len = u32::from_le_bytes(read_bytes(&mut reader)?);
+    skip(&mut reader, len as u64)?;
+  }
+
+  // tensors until the end of the file
+  let mut n_tensors = 0;
+  loop {
+    let mut n_dims = [0u8; 4];
+    let n = reader.read(&mut n_dims)?;
+    if n == 0 {
+      break;
+    }
+    if n < n_dims.len() {
+      reader
+        .read_exact(&mut n_dims[n..])
+        .map_err(|_| model_error("truncated tensor header"))?;
+    }
+    let n_dims = i32::from_le_bytes(n_dims);
+    let name_len = read_i32(&mut reader)?;
+    let ttype = read_i32(&mut reader)?;
+    if !(1..=sys::GGML_MAX_DIMS as i32).contains(&n_dims) || name_len < 0 {
+      return Err(model_error(format!("bad header of tensor #{n_tensors}")));
+    }
+    let mut n_elements = 1u64;
+    for _ in 0..n_dims {
+      n_elements = n_elements
+        .checked_mul(read_i32(&mut reader)?.max(0) as u64)
+        .ok_or_else(|| model_error(format!("bad shape of tensor #{n_tensors}")))?;
+    }
+    skip(&mut reader, name_len as u64)?;
+    let (block_size, type_size) = ggml_type_size(ttype)
+      .ok_or_else(|| model_error(format!("unknown type {ttype} of tensor #{n_tensors}")))?;
+    let size = (n_elements / block_size)
+      .checked_mul(type_size)
+      .ok_or_else(|| model_error(format!("bad shape of tensor #{n_tensors}")))?;
+    skip(&mut reader, size)?;
+    n_tensors += 1;
+  }
+
+  let qnt_version_factor = sys::GGML_QNT_VERSION_FACTOR as i32;
+  Ok(ModelInfo {
+    n_vocab: header.n_vocab,
+    n_audio_ctx: header.n_audio_ctx,
+    n_audio_state: header.n_audio_state,
+    n_audio_head: header.n_audio_head,
+    n_audio_layer: header.n_audio_layer,
+    n_text_ctx: header.n_text_ctx,
+    n_text_state: header.n_text_state,
+    n_text_head: header.n_text_head,
+    n_text_layer: header.n_text_layer,
+    n_mels: header.n_mels,
+    ftype: header.ftype % qnt_version_factor,
+    qnt_version: header.ftype / qnt_version_factor,
+    model_type: header.model_type().to_owned(),
+    is_multilingual: header.is_multilingual(),
+    n_tensors,
+    sha1: reader
+      .hasher
+      .finalize()
+      .iter()
+      .map(|byte| format!("{byte:02x}"))
+      .collect(),
+  })
+}
+
+fn inspect_model_source(model: Either<&[u8], &str>) -> Result<ModelInfo> {
+  match model {
+    Either::A(buf) => inspect(buf),
+    Either::B(filepath) => inspect(BufReader::with_capacity(1024 * 1024, File::open(filepath)?)),
+  }
+}
+
+#[napi]
+/// Read the hyper parameters of a model file or buffer and validate its layout without loading the weights.
+/// The whole file is read once for the checksum.
+pub fn inspect_model(model: Either<&[u8], String>) -> Result<ModelInfo> {
+  inspect_model_source(match &model {
+    Either::A(buf) => Either::A(*buf),
+    Either::B(filepath) => Either::B(filepath.as_str()),
+  })
+}
+
+pub struct InspectModelTask {
+  model: Either<Uint8Array, String>,
+}
+
+#[napi]
+impl Task for InspectModelTask {
+  type Output = ModelInfo;
+  type JsValue = ModelInfo;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    inspect_model_source(match &self.model {
+      Either::A(buf) => Either::A(&buf[..]),
+      Either::B(filepath) => Either::B(filepath.as_str()),
+    })
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+#[napi]
+/// Async version of `inspectModel`, running on the libuv threadpool
+pub fn inspect_model_async(
+  model: Either<Uint8Array, String>,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<InspectModelTask> {
+  AsyncTask::with_optional_signal(InspectModelTask { model }, signal)
+}
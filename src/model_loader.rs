@@ -0,0 +1,322 @@
+use std::{
+  collections::VecDeque,
+  ffi::c_void,
+  io::{self, Cursor, Read},
+  sync::{Arc, Condvar, Mutex, MutexGuard},
+};
+
+use napi::{bindgen_prelude::*, JsDeferred, Task};
+use napi_derive::napi;
+
+use crate::{
+  context_params::{ContextParams, WhisperContextParams},
+  model_header::{ModelHeader, MODEL_HEADER_SIZE},
+  sys, Whisper, WhisperContext,
+};
+
+/// Model source read by whisper.cpp through `whisper_model_loader`
+struct ModelReader {
+  reader: Box<dyn Read + Send>,
+  eof: bool,
+}
+
+extern "C" fn model_loader_read(ctx: *mut c_void, output: *mut c_void, read_size: usize) -> usize {
+  let model_reader = unsafe { &mut *ctx.cast::<ModelReader>() };
+  let output = unsafe { std::slice::from_raw_parts_mut(output.cast::<u8>(), read_size) };
+  let mut filled = 0;
+  while filled < read_size {
+    match model_reader.reader.read(&mut output[filled..]) {
+      Ok(0) | Err(_) => {
+        model_reader.eof = true;
+        break;
+      }
+      Ok(n) => filled += n,
+    }
+  }
+  filled
+}
+
+extern "C" fn model_loader_eof(ctx: *mut c_void) -> bool {
+  unsafe { &*ctx.cast::<ModelReader>() }.eof
+}
+
+extern "C" fn model_loader_close(_ctx: *mut c_void) {
+  // the reader is dropped by `WhisperContext::from_reader` once `whisper_init_with_params` returns
+}
+
+impl WhisperContext {
+  /// Load the model incrementally from `reader`, only whisper.cpp's own copy of the weights is kept in memory
+  pub(crate) fn from_reader(
+    mut reader: impl Read + Send + 'static,
+    params: Option<WhisperContextParams>,
+  ) -> Result<Self> {
+    // peek the header for the DTW alignment heads preset, the reader may not be seekable
+    let mut header = Vec::with_capacity(MODEL_HEADER_SIZE);
+    (&mut reader)
+      .take(MODEL_HEADER_SIZE as u64)
+      .read_to_end(&mut header)?;
+    let params = ContextParams::new(params, || ModelHeader::read(&header[..]))?;
+    let mut model_reader = ModelReader {
+      reader: Box::new(Cursor::new(header).chain(reader)),
+      eof: false,
+    };
+    let mut loader = sys::whisper_model_loader {
+      context: (&mut model_reader as *mut ModelReader).cast(),
+      read: Some(model_loader_read),
+      eof: Some(model_loader_eof),
+      close: Some(model_loader_close),
+    };
+    let inner = unsafe { sys::whisper_init_with_params(&mut loader, params.inner) };
+    drop(model_reader);
+    WhisperContext::new(
+      inner,
+      params,
+      "Failed to initialize Whisper model from stream",
+    )
+  }
+}
+
+type WriteDeferred = JsDeferred<(), fn(Env) -> Result<()>>;
+
+#[derive(Default)]
+struct ModelChannelState {
+  chunks: VecDeque<Vec<u8>>,
+  buffered: usize,
+  ended: bool,
+  destroyed: bool,
+  /// The loader stopped reading, either done or failed
+  closed: bool,
+  /// Writes waiting for the loader to read the buffered bytes below the `highWaterMark`
+  pending_writes: Vec<WriteDeferred>,
+}
+
+/// Chunks written from JS and read by the model loader on the libuv threadpool
+struct ModelChannel {
+  state: Mutex<ModelChannelState>,
+  changed: Condvar,
+  high_water_mark: usize,
+}
+
+impl ModelChannel {
+  fn lock(&self) -> MutexGuard<'_, ModelChannelState> {
+    self
+      .state
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  fn destroy(&self) {
+    let mut state = self.lock();
+    state.destroyed = true;
+    state.chunks.clear();
+    state.buffered = 0;
+    self.release_writes(&mut state);
+    self.changed.notify_all();
+  }
+
+  /// Resolve the pending writes once the loader caught up, or will never read again
+  fn release_writes(&self, state: &mut ModelChannelState) {
+    if state.buffered <= self.high_water_mark || state.closed || state.destroyed {
+      for deferred in state.pending_writes.drain(..) {
+        deferred.resolve(|_| Ok(()));
+      }
+    }
+  }
+
+  fn wait<'a>(
+    &self,
+    guard: MutexGuard<'a, ModelChannelState>,
+  ) -> MutexGuard<'a, ModelChannelState> {
+    self
+      .changed
+      .wait(guard)
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+}
+
+struct ModelChannelReader {
+  channel: Arc<ModelChannel>,
+  chunk: Vec<u8>,
+  pos: usize,
+}
+
+impl Read for ModelChannelReader {
+  fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    while self.pos >= self.chunk.len() {
+      let mut state = self.channel.lock();
+      loop {
+        if state.destroyed {
+          return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "The model stream was destroyed",
+          ));
+        }
+        if let Some(chunk) = state.chunks.pop_front() {
+          state.buffered -= chunk.len();
+          self.chunk = chunk;
+          self.pos = 0;
+          self.channel.release_writes(&mut state);
+          break;
+        }
+        if state.ended {
+          return Ok(0);
+        }
+        state = self.channel.wait(state);
+      }
+    }
+    let n = buf.len().min(self.chunk.len() - self.pos);
+    buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+    self.pos += n;
+    Ok(n)
+  }
+}
+
+impl Drop for ModelChannelReader {
+  fn drop(&mut self) {
+    let mut state = self.channel.lock();
+    state.closed = true;
+    state.chunks.clear();
+    state.buffered = 0;
+    self.channel.release_writes(&mut state);
+  }
+}
+
+#[napi]
+/// Writable end for `Whisper.fromStream`, e.g. to load the model from a Node.js `Readable`:
+/// ```js
+/// const stream = new WhisperModelStream()
+/// const loading = Whisper.fromStream(stream)
+/// for await (const chunk of createReadStream(modelPath)) await stream.write(chunk)
+/// stream.end()
+/// const whisper = await loading
+/// ```
+pub struct WhisperModelStream {
+  channel: Arc<ModelChannel>,
+}
+
+#[napi]
+impl WhisperModelStream {
+  #[napi(constructor)]
+  /// `highWaterMark` is the number of buffered bytes above which `write` waits for the loader, 16 MiB by default
+  pub fn new(high_water_mark: Option<u32>) -> Self {
+    Self {
+      channel: Arc::new(ModelChannel {
+        state: Mutex::new(ModelChannelState::default()),
+        changed: Condvar::new(),
+        high_water_mark: high_water_mark.unwrap_or(16 * 1024 * 1024) as usize,
+      }),
+    }
+  }
+
+  #[napi]
+  /// Queue a chunk of the model file, resolves once the buffered bytes are below the `highWaterMark`
+  pub fn write<'env>(&self, env: &'env Env, chunk: &[u8]) -> Result<PromiseRaw<'env, ()>> {
+    let mut state = self.channel.lock();
+    if state.ended {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "Cannot write to the model stream after end",
+      ));
+    }
+    // the loader failed or finished, the rest of the file is not needed
+    if !state.closed && !state.destroyed && !chunk.is_empty() {
+      state.buffered += chunk.len();
+      state.chunks.push_back(chunk.to_vec());
+      self.channel.changed.notify_all();
+    }
+    // resolved by the loader as it reads, no threadpool worker waits for it
+    let (deferred, promise) = env.create_deferred()?;
+    state.pending_writes.push(deferred);
+    self.channel.release_writes(&mut state);
+    Ok(promise)
+  }
+
+  #[napi]
+  /// Signal the end of the model file
+  pub fn end(&self) {
+    self.channel.lock().ended = true;
+    self.channel.changed.notify_all();
+  }
+
+  #[napi]
+  /// Abort the loading, `Whisper.fromStream` rejects
+  pub fn destroy(&self) {
+    self.channel.destroy();
+  }
+}
+
+impl Drop for WhisperModelStream {
+  fn drop(&mut self) {
+    // garbage collected before `end`, the loader would wait for the rest of the file forever
+    if !self.channel.lock().ended {
+      self.channel.destroy();
+    }
+  }
+}
+
+#[napi]
+impl Whisper {
+  #[napi(factory)]
+  /// Load the model from an open file descriptor, e.g. a pipe or a socket. The descriptor is not closed.
+  pub fn from_fd(fd: i32, params: Option<WhisperContextParams>) -> Result<Self> {
+    if fd < 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Invalid file descriptor {fd}"),
+      ));
+    }
+    #[cfg(unix)]
+    {
+      use std::os::fd::BorrowedFd;
+
+      let file = std::fs::File::from(unsafe { BorrowedFd::borrow_raw(fd) }.try_clone_to_owned()?);
+      Ok(Whisper::from_context(Arc::new(
+        WhisperContext::from_reader(io::BufReader::new(file), params)?,
+      )))
+    }
+    #[cfg(not(unix))]
+    {
+      let _ = (fd, params);
+      Err(Error::new(
+        Status::GenericFailure,
+        "Loading the model from a file descriptor is only supported on unix",
+      ))
+    }
+  }
+
+  #[napi]
+  /// Load the model from the chunks written to `stream` on the libuv threadpool
+  pub fn from_stream(
+    stream: &WhisperModelStream,
+    params: Option<WhisperContextParams>,
+  ) -> AsyncTask<LoadModelTask> {
+    AsyncTask::new(LoadModelTask {
+      channel: stream.channel.clone(),
+      params,
+    })
+  }
+}
+
+pub struct LoadModelTask {
+  channel: Arc<ModelChannel>,
+  params: Option<WhisperContextParams>,
+}
+
+#[napi]
+impl Task for LoadModelTask {
+  type Output = WhisperContext;
+  type JsValue = Whisper;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let reader = ModelChannelReader {
+      channel: self.channel.clone(),
+      chunk: Vec::new(),
+      pos: 0,
+    };
+    WhisperContext::from_reader(reader, self.params.take())
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(Whisper::from_context(Arc::new(output)))
+  }
+}
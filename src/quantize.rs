@@ -0,0 +1,336 @@
+// Port of whisper.cpp/examples/quantize/quantize.cpp and ggml_common_quantize_0 of examples/common-ggml.cpp
+
+use std::{
+  fs::{self, File},
+  io::{BufReader, BufWriter, Read, Write},
+};
+
+use napi::{bindgen_prelude::*, Task};
+use napi_derive::napi;
+
+use crate::{
+  log,
+  model_header::{model_error, read_bytes, read_i32, ModelHeader},
+  sys::{self, ggml_type},
+  WhisperLogLevel,
+};
+
+/// Tensors kept in full precision, same as the whisper.cpp quantize tool
+const TO_SKIP: [&str; 4] = [
+  "encoder.conv1.bias",
+  "encoder.conv2.bias",
+  "encoder.positional_embedding",
+  "decoder.positional_embedding",
+];
+
+/// Block size of the supported quantization types
+const QK: usize = 32;
+
+#[napi]
+#[derive(Debug, Clone, Copy)]
+/// Target weight type of `quantizeModel`, the values are the `ggml_ftype` accepted by the whisper.cpp quantize tool
+pub enum WhisperQuantizationType {
+  Q4_0 = 2,
+  Q4_1 = 3,
+  Q8_0 = 7,
+  Q5_0 = 8,
+  Q5_1 = 9,
+}
+
+impl WhisperQuantizationType {
+  fn ggml_type(self) -> ggml_type::GgmlType {
+    match self {
+      Self::Q4_0 => ggml_type::GGML_TYPE_Q4_0,
+      Self::Q4_1 => ggml_type::GGML_TYPE_Q4_1,
+      Self::Q8_0 => ggml_type::GGML_TYPE_Q8_0,
+      Self::Q5_0 => ggml_type::GGML_TYPE_Q5_0,
+      Self::Q5_1 => ggml_type::GGML_TYPE_Q5_1,
+    }
+  }
+}
+
+fn write_i32(writer: &mut impl Write, value: i32) -> Result<()> {
+  writer.write_all(&value.to_le_bytes())?;
+  Ok(())
+}
+
+fn copy_exact(reader: &mut impl Read, writer: &mut impl Write, n: u64) -> Result<()> {
+  let copied = std::io::copy(&mut reader.take(n), writer)?;
+  if copied != n {
+    return Err(model_error("unexpected end of file"));
+  }
+  Ok(())
+}
+
+fn quantize(
+  reader: &mut impl Read,
+  writer: &mut impl Write,
+  quantization_type: WhisperQuantizationType,
+) -> Result<()> {
+  let header = ModelHeader::read(&mut *reader)?;
+  let qtype = quantization_type.ggml_type();
+  let ftype =
+    sys::GGML_QNT_VERSION as i32 * sys::GGML_QNT_VERSION_FACTOR as i32 + quantization_type as i32;
+
+  writer.write_all(&sys::GGML_FILE_MAGIC.to_le_bytes())?;
+  for hparam in [
+    header.n_vocab,
+    header.n_audio_ctx,
+    header.n_audio_state,
+    header.n_audio_head,
+    header.n_audio_layer,
+    header.n_text_ctx,
+    header.n_text_state,
+    header.n_text_head,
+    header.n_text_layer,
+    header.n_mels,
+    ftype,
+  ] {
+    write_i32(writer, hparam)?;
+  }
+
+  // mel filters
+  let n_mel = read_i32(reader)?;
+  let n_fft = read_i32(reader)?;
+  write_i32(writer, n_mel)?;
+  write_i32(writer, n_fft)?;
+  copy_exact(
+    reader,
+    writer,
+    4 * n_mel.max(0) as u64 * n_fft.max(0) as u64,
+  )?;
+
+  // vocab
+  let n_vocab = read_i32(reader)?;
+  write_i32(writer, n_vocab)?;
+  for _ in 0..n_vocab.max(0) {
+    let len = u32::from_le_bytes(read_bytes(reader)?);
+    writer.write_all(&len.to_le_bytes())?;
+    copy_exact(reader, writer, len as u64)?;
+  }
+
+  // needed to initialize the f16 tables, same as the quantize tool
+  unsafe {
+    let ctx = sys::ggml_init(sys::ggml_init_params {
+      mem_size: 0,
+      mem_buffer: std::ptr::null_mut(),
+      no_alloc: false,
+    });
+    sys::ggml_free(ctx);
+  }
+
+  // tensors until the end of the file
+  let mut total_size_org = 0usize;
+  let mut total_size_new = 0usize;
+  let mut data = Vec::<u8>::new();
+  let mut data_f16 = Vec::<u16>::new();
+  let mut data_f32 = Vec::<f32>::new();
+  // the quantized blocks hold f16 scales, so the output needs more than byte alignment
+  let mut work = Vec::<f32>::new();
+  loop {
+    let mut n_dims = [0u8; 4];
+    let n = reader.read(&mut n_dims)?;
+    if n == 0 {
+      break;
+    }
+    if n < n_dims.len() {
+      reader
+        .read_exact(&mut n_dims[n..])
+        .map_err(|_| model_error("truncated tensor header"))?;
+    }
+    let n_dims = i32::from_le_bytes(n_dims);
+    let name_len = read_i32(reader)?;
+    let mut ttype = read_i32(reader)? as ggml_type::GgmlType;
+    if !(1..=sys::GGML_MAX_DIMS as i32).contains(&n_dims) || name_len < 0 {
+      return Err(model_error("bad tensor header"));
+    }
+    let mut ne = [1i32; 4];
+    for ne in ne.iter_mut().take(n_dims as usize) {
+      *ne = read_i32(reader)?;
+    }
+    let n_elements = ne
+      .iter()
+      .try_fold(1usize, |n, ne| n.checked_mul((*ne).max(0) as usize))
+      .filter(|n| n.checked_mul(4).is_some())
+      .ok_or_else(|| model_error("bad tensor shape"))?;
+    // the name is written back as is, it is only decoded for the logs
+    let mut name_bytes = vec![0u8; name_len as usize];
+    reader.read_exact(&mut name_bytes)?;
+    let name = String::from_utf8_lossy(&name_bytes).into_owned();
+
+    // quantize only 2D tensors
+    let mut quantized = n_dims == 2 && !TO_SKIP.iter().any(|skip| skip.as_bytes() == name_bytes);
+    if quantized && (ne[0] <= 0 || ne[0] as usize % QK != 0) {
+      log(
+        WhisperLogLevel::Warn,
+        &format!(
+          "{name}: row size {} is not a multiple of {QK}, not quantized",
+          ne[0]
+        ),
+      );
+      quantized = false;
+    }
+    let (type_name, bytes_per_element) = match ttype {
+      ggml_type::GGML_TYPE_F32 => ("f32", 4),
+      ggml_type::GGML_TYPE_F16 => ("f16", 2),
+      _ => {
+        return Err(model_error(format!(
+          "unsupported type {ttype} of tensor {name}, the model is already quantized"
+        )))
+      }
+    };
+    let mut message = format!(
+      "{name:>64} - [{:5}, {:5}, {:5}], type = {type_name:>6} ",
+      ne[0], ne[1], ne[2],
+    );
+    // read as it comes instead of allocating the size of the header, which may not match the file
+    let n_bytes = (n_elements * bytes_per_element) as u64;
+    data.clear();
+    if reader.by_ref().take(n_bytes).read_to_end(&mut data)? as u64 != n_bytes {
+      return Err(model_error("unexpected end of file"));
+    }
+    if quantized {
+      data_f32.resize(n_elements, 0.0);
+      if ttype == ggml_type::GGML_TYPE_F16 {
+        data_f16.clear();
+        data_f16.extend(
+          data
+            .chunks_exact(2)
+            .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]])),
+        );
+        unsafe {
+          sys::ggml_fp16_to_fp32_row(
+            data_f16.as_ptr().cast(),
+            data_f32.as_mut_ptr(),
+            n_elements as i64,
+          )
+        };
+      } else {
+        for (value, bytes) in data_f32.iter_mut().zip(data.chunks_exact(4)) {
+          *value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        }
+      }
+      ttype = qtype;
+    }
+
+    write_i32(writer, n_dims)?;
+    write_i32(writer, name_len)?;
+    write_i32(writer, ttype as i32)?;
+    for ne in ne.iter().take(n_dims as usize) {
+      write_i32(writer, *ne)?;
+    }
+    writer.write_all(&name_bytes)?;
+
+    if quantized {
+      work.resize(n_elements, 0.0);
+      let size = unsafe {
+        sys::ggml_quantize_chunk(
+          qtype,
+          data_f32.as_ptr(),
+          work.as_mut_ptr().cast(),
+          0,
+          (n_elements / ne[0] as usize) as i64,
+          ne[0] as i64,
+          std::ptr::null(),
+        )
+      };
+      writer.write_all(unsafe { std::slice::from_raw_parts(work.as_ptr().cast::<u8>(), size) })?;
+      total_size_new += size;
+      message.push_str(&format!(
+        "size = {:8.2} MB -> {:8.2} MB",
+        (n_elements * 4) as f64 / 1024.0 / 1024.0,
+        size as f64 / 1024.0 / 1024.0
+      ));
+    } else {
+      writer.write_all(&data)?;
+      total_size_new += data.len();
+      message.push_str(&format!(
+        "size = {:8.3} MB",
+        data.len() as f64 / 1024.0 / 1024.0
+      ));
+    }
+    total_size_org += n_elements * 4;
+    log(WhisperLogLevel::Info, &message);
+  }
+  writer.flush()?;
+
+  log(
+    WhisperLogLevel::Info,
+    &format!(
+      "model size  = {:8.2} MB",
+      total_size_org as f64 / 1024.0 / 1024.0
+    ),
+  );
+  log(
+    WhisperLogLevel::Info,
+    &format!(
+      "quant size  = {:8.2} MB | ftype = {} ({quantization_type:?})",
+      total_size_new as f64 / 1024.0 / 1024.0,
+      quantization_type as i32
+    ),
+  );
+  Ok(())
+}
+
+fn quantize_file(
+  input: &str,
+  output: &str,
+  quantization_type: WhisperQuantizationType,
+) -> Result<()> {
+  let mut reader = BufReader::with_capacity(1024 * 1024, File::open(input)?);
+  let mut writer = BufWriter::with_capacity(1024 * 1024, File::create(output)?);
+  let result = quantize(&mut reader, &mut writer, quantization_type);
+  drop(writer);
+  if result.is_err() {
+    let _ = fs::remove_file(output);
+  }
+  result
+}
+
+#[napi]
+/// Quantize an f16 or f32 ggml model file like the whisper.cpp quantize tool, the progress of every tensor is logged to `setupLogger`
+pub fn quantize_model(
+  input: String,
+  output: String,
+  quantization_type: WhisperQuantizationType,
+) -> Result<()> {
+  quantize_file(&input, &output, quantization_type)
+}
+
+pub struct QuantizeModelTask {
+  input: String,
+  output: String,
+  quantization_type: WhisperQuantizationType,
+}
+
+#[napi]
+impl Task for QuantizeModelTask {
+  type Output = ();
+  type JsValue = ();
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    quantize_file(&self.input, &self.output, self.quantization_type)
+  }
+
+  fn resolve(&mut self, _: Env, _: Self::Output) -> Result<Self::JsValue> {
+    Ok(())
+  }
+}
+
+#[napi]
+/// Async version of `quantizeModel`, running on the libuv threadpool
+pub fn quantize_model_async(
+  input: String,
+  output: String,
+  quantization_type: WhisperQuantizationType,
+  signal: Option<AbortSignal>,
+) -> AsyncTask<QuantizeModelTask> {
+  AsyncTask::with_optional_signal(
+    QuantizeModelTask {
+      input,
+      output,
+      quantization_type,
+    },
+    signal,
+  )
+}
@@ -0,0 +1,208 @@
+use std::ffi::CStr;
+
+use napi_derive::napi;
+
+use crate::{full_params::Segment, sys};
+
+#[napi(object)]
+/// Result of a `whisper_full` run
+pub struct WhisperFullResult {
+  /// Id of the detected (or requested) language
+  pub lang_id: i32,
+  /// Short string of the language, e.g. "en"
+  pub language: Option<String>,
+  pub segments: Vec<Segment>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+/// Token level data of a segment, see `whisper_token_data`
+pub struct Token {
+  pub id: i32,
+  /// Id of the forced timestamp token
+  pub timestamp_id: i32,
+  /// Text of the token, a multi-byte character split across tokens shows up as U+FFFD in each of them
+  pub text: String,
+  /// Raw bytes of the text, joining them across tokens decodes the split characters
+  pub bytes: Vec<u8>,
+  /// Special tokens like `[_BEG_]` and the timestamps are not part of the text
+  pub special: bool,
+  /// Probability of the token
+  pub p: f64,
+  /// Log probability of the token
+  pub plog: f64,
+  /// Probability of the timestamp token
+  pub pt: f64,
+  /// Sum of probabilities of all timestamp tokens
+  pub ptsum: f64,
+  /// Start time in milliseconds, requires `tokenTimestamps`
+  pub start: Option<u32>,
+  /// End time in milliseconds, requires `tokenTimestamps`
+  pub end: Option<u32>,
+  /// DTW timestamp in milliseconds, requires `dtwTokenTimestamps` on the context
+  pub dtw_timestamp: Option<u32>,
+  /// Voice length of the token
+  pub vlen: f64,
+}
+
+#[derive(Clone, Copy)]
+/// Where the results of a `whisper_full` run are read from
+pub(crate) enum ResultSource {
+  /// The default state of the context
+  Context(*mut sys::whisper_context),
+  State(*mut sys::whisper_context, *mut sys::whisper_state),
+}
+
+// whisper.cpp timestamps are in units of 10 ms, -1 if they were not computed
+fn timestamp_ms(t: i64) -> Option<u32> {
+  (t >= 0).then_some((t * 10) as u32)
+}
+
+impl ResultSource {
+  fn ctx(self) -> *mut sys::whisper_context {
+    match self {
+      Self::Context(ctx) | Self::State(ctx, _) => ctx,
+    }
+  }
+
+  pub(crate) fn n_segments(self) -> i32 {
+    match self {
+      Self::Context(ctx) => unsafe { sys::whisper_full_n_segments(ctx) },
+      Self::State(_, state) => unsafe { sys::whisper_full_n_segments_from_state(state) },
+    }
+  }
+
+  pub(crate) fn lang_id(self) -> i32 {
+    match self {
+      Self::Context(ctx) => unsafe { sys::whisper_full_lang_id(ctx) },
+      Self::State(_, state) => unsafe { sys::whisper_full_lang_id_from_state(state) },
+    }
+  }
+
+  pub(crate) fn segment_text(self, i_segment: i32) -> String {
+    let text = match self {
+      Self::Context(ctx) => unsafe { sys::whisper_full_get_segment_text(ctx, i_segment) },
+      Self::State(_, state) => unsafe {
+        sys::whisper_full_get_segment_text_from_state(state, i_segment)
+      },
+    };
+    if text.is_null() {
+      return String::new();
+    }
+    unsafe { CStr::from_ptr(text) }
+      .to_string_lossy()
+      .into_owned()
+  }
+
+  pub(crate) fn segment(self, i_segment: i32) -> Segment {
+    let (t0, t1, no_speech_prob, speaker_turn_next) = match self {
+      Self::Context(ctx) => unsafe {
+        (
+          sys::whisper_full_get_segment_t0(ctx, i_segment),
+          sys::whisper_full_get_segment_t1(ctx, i_segment),
+          sys::whisper_full_get_segment_no_speech_prob(ctx, i_segment),
+          sys::whisper_full_get_segment_speaker_turn_next(ctx, i_segment),
+        )
+      },
+      Self::State(_, state) => unsafe {
+        (
+          sys::whisper_full_get_segment_t0_from_state(state, i_segment),
+          sys::whisper_full_get_segment_t1_from_state(state, i_segment),
+          sys::whisper_full_get_segment_no_speech_prob_from_state(state, i_segment),
+          sys::whisper_full_get_segment_speaker_turn_next_from_state(state, i_segment),
+        )
+      },
+    };
+    Segment {
+      index: i_segment as u32,
+      text: self.segment_text(i_segment).trim().to_string(),
+      // whisper.cpp timestamps are in units of 10 ms
+      start: (t0 * 10) as u32,
+      end: (t1 * 10) as u32,
+      no_speech_prob: no_speech_prob as f64,
+      speaker_turn_next,
+      speaker: None,
+      temperature: None,
+      tokens: self.tokens(i_segment),
+    }
+  }
+
+  pub(crate) fn n_tokens(self, i_segment: i32) -> i32 {
+    match self {
+      Self::Context(ctx) => unsafe { sys::whisper_full_n_tokens(ctx, i_segment) },
+      Self::State(_, state) => unsafe { sys::whisper_full_n_tokens_from_state(state, i_segment) },
+    }
+  }
+
+  /// Raw bytes of the token text, a multi-byte character can be split across tokens
+  pub(crate) fn token_bytes(self, i_segment: i32, i_token: i32) -> Vec<u8> {
+    let text = match self {
+      Self::Context(ctx) => unsafe { sys::whisper_full_get_token_text(ctx, i_segment, i_token) },
+      Self::State(ctx, state) => unsafe {
+        sys::whisper_full_get_token_text_from_state(ctx, state, i_segment, i_token)
+      },
+    };
+    if text.is_null() {
+      return Vec::new();
+    }
+    unsafe { CStr::from_ptr(text) }.to_bytes().to_vec()
+  }
+
+  pub(crate) fn token(self, i_segment: i32, i_token: i32) -> Token {
+    let data = match self {
+      Self::Context(ctx) => unsafe { sys::whisper_full_get_token_data(ctx, i_segment, i_token) },
+      Self::State(_, state) => unsafe {
+        sys::whisper_full_get_token_data_from_state(state, i_segment, i_token)
+      },
+    };
+    let bytes = self.token_bytes(i_segment, i_token);
+    Token {
+      id: data.id,
+      timestamp_id: data.tid,
+      text: String::from_utf8_lossy(&bytes).into_owned(),
+      bytes,
+      special: data.id >= unsafe { sys::whisper_token_eot(self.ctx()) },
+      p: data.p as f64,
+      plog: data.plog as f64,
+      pt: data.pt as f64,
+      ptsum: data.ptsum as f64,
+      start: timestamp_ms(data.t0),
+      end: timestamp_ms(data.t1),
+      dtw_timestamp: timestamp_ms(data.t_dtw),
+      vlen: data.vlen as f64,
+    }
+  }
+
+  pub(crate) fn tokens(self, i_segment: i32) -> Vec<Token> {
+    (0..self.n_tokens(i_segment))
+      .map(|i| self.token(i_segment, i))
+      .collect()
+  }
+
+  pub(crate) fn segments(self) -> Vec<Segment> {
+    (0..self.n_segments()).map(|i| self.segment(i)).collect()
+  }
+
+  /// All segment texts joined together, as returned by `Whisper.full`
+  pub(crate) fn text(self) -> String {
+    let mut output = String::with_capacity(1024);
+    for i in 0..self.n_segments() {
+      output.push_str(&self.segment_text(i));
+    }
+    output
+  }
+
+  pub(crate) fn full_result(self) -> WhisperFullResult {
+    let lang_id = self.lang_id();
+    let language = unsafe { sys::whisper_lang_str(lang_id) };
+    WhisperFullResult {
+      lang_id,
+      language: (!language.is_null()).then(|| {
+        unsafe { CStr::from_ptr(language) }
+          .to_string_lossy()
+          .into_owned()
+      }),
+      segments: self.segments(),
+    }
+  }
+}
@@ -1,23 +1,156 @@
+use std::{
+  ptr,
+  sync::{Arc, Mutex, MutexGuard},
+};
+
+use napi::bindgen_prelude::*;
 use napi_derive::napi;
 
-use crate::sys;
+use crate::{
+  result::{ResultSource, WhisperFullResult},
+  sys, try_lock_run, WhisperContext,
+};
 
 #[napi]
+/// Decoding state of a run. The state passed to `onEncoderBegin` is a view with only `fullLangId` and `nLen`,
+/// its results throw because the run is still writing them.
 pub struct WhisperState {
   pub(crate) inner: *mut sys::whisper_state,
+  pub(crate) ctx: *mut sys::whisper_context,
+  /// Set for the states created by `Whisper.createState`, they keep the model alive and are freed on drop
+  pub(crate) owner: Option<Arc<WhisperContext>>,
+  lock: Mutex<()>,
+  /// Set for the views passed to the callbacks, see `borrowed`
+  view: Option<StateView>,
+}
+
+/// Values copied from a state owned by whisper.cpp, which keeps running on the threadpool
+/// and may be freed before the view is read
+#[derive(Clone, Copy)]
+struct StateView {
+  lang_id: i32,
+  n_len: i32,
+}
+
+// An owned state is only used by one `whisper_full_with_state` call at a time, guarded by `lock`
+unsafe impl Send for WhisperState {}
+unsafe impl Sync for WhisperState {}
+
+impl Drop for WhisperState {
+  fn drop(&mut self) {
+    if self.owner.is_some() {
+      unsafe { sys::whisper_free_state(self.inner) };
+    }
+  }
+}
+
+impl WhisperState {
+  /// View of a state owned by whisper.cpp, e.g. the one passed to the callbacks.
+  /// Must be created on the thread running on the state, it only keeps the language id and the mel length.
+  pub(crate) fn borrowed(ctx: *mut sys::whisper_context, state: *mut sys::whisper_state) -> Self {
+    let view = unsafe {
+      StateView {
+        lang_id: sys::whisper_full_lang_id_from_state(state),
+        n_len: sys::whisper_n_len_from_state(state),
+      }
+    };
+    Self {
+      inner: ptr::null_mut(),
+      ctx,
+      owner: None,
+      lock: Mutex::new(()),
+      view: Some(view),
+    }
+  }
+
+  pub(crate) fn new(ctx: Arc<WhisperContext>) -> Result<Self> {
+    let inner = unsafe { sys::whisper_init_state(ctx.inner) };
+    if inner.is_null() {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "Failed to initialize Whisper state",
+      ));
+    }
+    Ok(Self {
+      inner,
+      ctx: ctx.inner,
+      owner: Some(ctx),
+      lock: Mutex::new(()),
+      view: None,
+    })
+  }
+
+  pub(crate) fn lock(&self) -> MutexGuard<'_, ()> {
+    self
+      .lock
+      .lock()
+      .unwrap_or_else(|poisoned| poisoned.into_inner())
+  }
+
+  /// Same as `lock` for the JS thread, fails instead of waiting for a run on the threadpool
+  pub(crate) fn try_lock(&self) -> Result<MutexGuard<'_, ()>> {
+    try_lock_run(&self.lock)
+  }
+
+  pub(crate) fn result_source(&self) -> ResultSource {
+    ResultSource::State(self.ctx, self.inner)
+  }
+
+  /// The results of a view would be read while whisper.cpp writes them
+  fn assert_not_view(&self) -> Result<()> {
+    if self.view.is_some() {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "The state passed to the callbacks only has fullLangId and nLen, read the results from the run",
+      ));
+    }
+    Ok(())
+  }
 }
 
 #[napi]
 impl WhisperState {
   #[napi(getter)]
   /// Language id associated with the provided state
-  pub fn get_full_lang_id(&self) -> i32 {
-    unsafe { sys::whisper_full_lang_id_from_state(self.inner) }
+  pub fn get_full_lang_id(&self) -> Result<i32> {
+    if let Some(view) = self.view {
+      return Ok(view.lang_id);
+    }
+    let _guard = self.try_lock()?;
+    Ok(unsafe { sys::whisper_full_lang_id_from_state(self.inner) })
   }
 
   #[napi(getter)]
   /// mel length
-  pub fn get_n_len(&self) -> i32 {
-    unsafe { sys::whisper_n_len_from_state(self.inner) }
+  pub fn get_n_len(&self) -> Result<i32> {
+    if let Some(view) = self.view {
+      return Ok(view.n_len);
+    }
+    let _guard = self.try_lock()?;
+    Ok(unsafe { sys::whisper_n_len_from_state(self.inner) })
+  }
+
+  #[napi(getter)]
+  /// Number of segments produced by the last run on this state
+  pub fn get_n_segments(&self) -> Result<i32> {
+    self.assert_not_view()?;
+    let _guard = self.try_lock()?;
+    Ok(self.result_source().n_segments())
+  }
+
+  #[napi]
+  /// All segment texts of the last run on this state joined together
+  pub fn text(&self) -> Result<String> {
+    self.assert_not_view()?;
+    let _guard = self.try_lock()?;
+    Ok(self.result_source().text())
+  }
+
+  #[napi]
+  /// Segments of the last run on this state
+  pub fn result(&self) -> Result<WhisperFullResult> {
+    self.assert_not_view()?;
+    let _guard = self.try_lock()?;
+    Ok(self.result_source().full_result())
   }
 }
@@ -0,0 +1,321 @@
+// Sliding window transcription of whisper.cpp/examples/stream/stream.cpp
+
+use std::sync::{atomic::AtomicBool, Arc, Mutex, MutexGuard};
+
+use napi::{bindgen_prelude::*, Task};
+use napi_derive::napi;
+
+use crate::{
+  check_full_status,
+  full_params::{Segment, WhisperFullParams},
+  sys, watch_abort_signal, Whisper, WhisperContext, WhisperState, WHISPER_SAMPLE_RATE,
+};
+
+#[napi(object)]
+#[derive(Default)]
+pub struct WhisperStreamOptions {
+  /// Audio decoded per step in milliseconds, 3000 by default
+  pub step_ms: Option<u32>,
+  /// Length of the sliding window in milliseconds, 10000 by default
+  pub length_ms: Option<u32>,
+  /// Audio of the finalized window kept for the next one in milliseconds, 200 by default
+  pub keep_ms: Option<u32>,
+  /// Use the tokens of the finalized window as prompt of the next one, true by default
+  pub keep_context: Option<bool>,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+/// Transcription of the sliding window after a step
+pub struct WhisperStreamUpdate {
+  /// A finalized window is not decoded again, a partial one is replaced by the next update
+  pub is_final: bool,
+  /// Start of the window in milliseconds since the start of the stream
+  pub start: u32,
+  /// End of the window in milliseconds since the start of the stream
+  pub end: u32,
+  /// Segments of the window with timestamps since the start of the stream
+  pub segments: Vec<Segment>,
+}
+
+fn samples_ms(n_samples: u64) -> u32 {
+  (n_samples * 1000 / WHISPER_SAMPLE_RATE as u64) as u32
+}
+
+fn ms_samples(ms: u32) -> usize {
+  (ms as u64 * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+  mutex
+    .lock()
+    .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+struct StreamDecoder {
+  state: WhisperState,
+  /// Audio of the current window, the tail of it once the window is finalized
+  window: Vec<f32>,
+  prompt_tokens: Vec<i32>,
+  n_iter: u32,
+  /// Samples decoded since the start of the stream
+  n_decoded: u64,
+  /// Last update, if it was not finalized
+  partial: Option<WhisperStreamUpdate>,
+}
+
+struct StreamInner {
+  ctx: Arc<WhisperContext>,
+  /// Pushed samples not decoded yet
+  pending: Mutex<Vec<f32>>,
+  decoder: Mutex<StreamDecoder>,
+  n_samples_step: usize,
+  n_samples_len: usize,
+  n_samples_keep: usize,
+  /// Number of steps per window
+  n_new_line: u32,
+  keep_context: bool,
+}
+
+impl StreamInner {
+  fn step(
+    &self,
+    decoder: &mut StreamDecoder,
+    params: &WhisperFullParams,
+    new: &[f32],
+    last: bool,
+  ) -> Result<WhisperStreamUpdate> {
+    // take up to `length` of the audio, starting with the tail of the previous window
+    let n_samples_take = decoder
+      .window
+      .len()
+      .min((self.n_samples_keep + self.n_samples_len).saturating_sub(new.len()));
+    decoder
+      .window
+      .drain(..decoder.window.len() - n_samples_take);
+    decoder.window.extend_from_slice(new);
+    decoder.n_decoded += new.len() as u64;
+
+    let start = samples_ms(decoder.n_decoded - decoder.window.len() as u64);
+    let mut inner = params.inner.clone();
+    if self.keep_context && !decoder.prompt_tokens.is_empty() {
+      inner.prompt_tokens = decoder.prompt_tokens.as_ptr();
+      inner.prompt_n_tokens = decoder.prompt_tokens.len() as i32;
+    }
+    params.reset_fallback(true);
+    params.restart_progress();
+    params.set_time_offset(start);
+    let samples = params.speech_samples(&decoder.window);
+    let status = unsafe {
+      sys::whisper_full_with_state(
+        self.ctx.inner,
+        decoder.state.inner,
+        inner,
+        samples.as_ptr().cast(),
+        samples.len() as i32,
+      )
+    };
+    let segments = check_full_status(status, params).map(|()| {
+      let mut segments = decoder.state.result_source().segments();
+      params.fill_segments(&mut segments);
+      segments
+    });
+    params.set_time_offset(0);
+    let segments = segments?;
+    decoder.n_iter += 1;
+    let mut update = WhisperStreamUpdate {
+      is_final: false,
+      start,
+      end: samples_ms(decoder.n_decoded),
+      segments,
+    };
+    if last || decoder.n_iter % self.n_new_line == 0 {
+      self.finalize(decoder, &mut update);
+    } else {
+      decoder.partial = Some(update.clone());
+    }
+    Ok(update)
+  }
+
+  fn finalize(&self, decoder: &mut StreamDecoder, update: &mut WhisperStreamUpdate) {
+    update.is_final = true;
+    decoder.partial = None;
+    decoder.n_iter = 0;
+    // keep part of the audio for the next window to mitigate word boundary issues
+    let n_samples_keep = decoder.window.len().min(self.n_samples_keep);
+    decoder
+      .window
+      .drain(..decoder.window.len() - n_samples_keep);
+    if self.keep_context {
+      // the text tokens of the window, at most as many as whisper.cpp keeps from a prompt
+      let eot = unsafe { sys::whisper_token_eot(self.ctx.inner) };
+      let n_max = (unsafe { sys::whisper_n_text_ctx(self.ctx.inner) } / 2).max(0) as usize;
+      let tokens = update
+        .segments
+        .iter()
+        .flat_map(|segment| segment.tokens.iter().map(|token| token.id))
+        .filter(|token| *token < eot)
+        .collect::<Vec<_>>();
+      decoder.prompt_tokens = tokens[tokens.len().saturating_sub(n_max)..].to_vec();
+    }
+  }
+
+  /// Decode the pushed audio step by step, `flush` decodes the rest and finalizes the window.
+  /// `aborted` only stops this call, the next `push` decodes again.
+  fn process(
+    &self,
+    params: &WhisperFullParams,
+    aborted: Arc<AtomicBool>,
+    flush: bool,
+  ) -> Result<Vec<WhisperStreamUpdate>> {
+    let mut decoder = lock(&self.decoder);
+    let _run = params.begin_run(Some(aborted))?;
+    let mut updates = Vec::new();
+    loop {
+      let new = {
+        let mut pending = lock(&self.pending);
+        let n_samples = if pending.len() >= self.n_samples_step {
+          self.n_samples_step
+        } else if flush {
+          pending.len()
+        } else {
+          0
+        };
+        if n_samples == 0 {
+          break;
+        }
+        pending.drain(..n_samples).collect::<Vec<_>>()
+      };
+      let last = flush && lock(&self.pending).is_empty();
+      updates.push(self.step(&mut decoder, params, &new, last)?);
+    }
+    if flush {
+      if let Some(mut update) = decoder.partial.take() {
+        self.finalize(&mut decoder, &mut update);
+        updates.push(update);
+      }
+    }
+    Ok(updates)
+  }
+}
+
+#[napi]
+/// Live transcription of pushed audio with a sliding window, like the whisper.cpp stream example.
+/// Every step decodes the window on its own state, the window is finalized after `lengthMs` of audio
+/// and the next one starts with the last `keepMs` of it.
+/// The callbacks of the params fire for every step, `onNewSegment` with timestamps since the start of the stream.
+pub struct WhisperStream {
+  inner: Arc<StreamInner>,
+  params: Reference<WhisperFullParams>,
+}
+
+#[napi]
+impl WhisperStream {
+  #[napi(constructor)]
+  pub fn new(
+    whisper: &Whisper,
+    params: Reference<WhisperFullParams>,
+    options: Option<WhisperStreamOptions>,
+  ) -> Result<Self> {
+    let options = options.unwrap_or_default();
+    let step_ms = options.step_ms.unwrap_or(3000);
+    if step_ms == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "stepMs must be greater than 0",
+      ));
+    }
+    // same adjustments as the stream example
+    let keep_ms = options.keep_ms.unwrap_or(200).min(step_ms);
+    let length_ms = options.length_ms.unwrap_or(10000).max(step_ms);
+    Ok(Self {
+      inner: Arc::new(StreamInner {
+        ctx: whisper.ctx.clone(),
+        pending: Mutex::new(Vec::new()),
+        decoder: Mutex::new(StreamDecoder {
+          state: WhisperState::new(whisper.ctx.clone())?,
+          window: Vec::new(),
+          prompt_tokens: Vec::new(),
+          n_iter: 0,
+          n_decoded: 0,
+          partial: None,
+        }),
+        n_samples_step: ms_samples(step_ms),
+        n_samples_len: ms_samples(length_ms),
+        n_samples_keep: ms_samples(keep_ms),
+        n_new_line: (length_ms / step_ms).saturating_sub(1).max(1),
+        keep_context: options.keep_context.unwrap_or(true),
+      }),
+      params,
+    })
+  }
+
+  #[napi]
+  /// Queue 16 kHz mono samples, resolves with the updates of the steps decoded on the libuv threadpool
+  pub fn push(
+    &self,
+    env: Env,
+    samples: &[f32],
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<WhisperStreamTask>> {
+    lock(&self.inner.pending).extend_from_slice(samples);
+    self.task(env, false, signal)
+  }
+
+  #[napi]
+  /// Decode the rest of the queued audio and finalize the current window
+  pub fn flush(
+    &self,
+    env: Env,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<WhisperStreamTask>> {
+    self.task(env, true, signal)
+  }
+
+  fn task(
+    &self,
+    env: Env,
+    flush: bool,
+    signal: Option<AbortSignal>,
+  ) -> Result<AsyncTask<WhisperStreamTask>> {
+    let aborted = watch_abort_signal(signal.as_ref());
+    Ok(AsyncTask::with_optional_signal(
+      WhisperStreamTask {
+        inner: self.inner.clone(),
+        params: self.params.clone(env)?,
+        aborted,
+        flush,
+      },
+      signal,
+    ))
+  }
+
+  #[napi(getter)]
+  /// Milliseconds of audio queued and not decoded yet
+  pub fn get_pending_ms(&self) -> u32 {
+    samples_ms(lock(&self.inner.pending).len() as u64)
+  }
+}
+
+pub struct WhisperStreamTask {
+  inner: Arc<StreamInner>,
+  params: Reference<WhisperFullParams>,
+  aborted: Arc<AtomicBool>,
+  flush: bool,
+}
+
+#[napi]
+impl Task for WhisperStreamTask {
+  type Output = Vec<WhisperStreamUpdate>;
+  type JsValue = Vec<WhisperStreamUpdate>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    self
+      .inner
+      .process(&self.params, self.aborted.clone(), self.flush)
+  }
+
+  fn resolve(&mut self, _: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
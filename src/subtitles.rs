@@ -0,0 +1,355 @@
+use std::fmt::Write;
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{full_params::Segment, words::segment_words};
+
+#[napi]
+#[derive(Debug, Clone, Copy)]
+pub enum SubtitleFormat {
+  Srt = 0,
+  WebVtt = 1,
+  Lrc = 2,
+  /// Advanced SubStation Alpha
+  Ass = 3,
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+/// Style of the ASS output, colours are in the `&HAABBGGRR` notation of ASS
+pub struct AssStyle {
+  /// "Default" for the first style and "Style2", "Style3"... for the next ones by default
+  pub name: Option<String>,
+  /// "Arial" by default
+  pub font_name: Option<String>,
+  /// Font size on the 1920x1080 script resolution, 64 by default
+  pub font_size: Option<u32>,
+  /// Colour of the text, and of the words already spoken with `wordTimestamps`, "&H00FFFFFF" by default
+  pub primary_colour: Option<String>,
+  /// Colour of the words not spoken yet with `wordTimestamps`, "&H00A0A0A0" by default
+  pub secondary_colour: Option<String>,
+  /// "&H00000000" by default
+  pub outline_colour: Option<String>,
+  /// Colour of the shadow, "&H80000000" by default
+  pub back_colour: Option<String>,
+  pub bold: Option<bool>,
+  pub italic: Option<bool>,
+  /// Width of the outline in pixels, 3 by default
+  pub outline: Option<f64>,
+  /// Depth of the shadow in pixels, 0 by default
+  pub shadow: Option<f64>,
+  /// Position on the numpad, 2 (bottom center) by default
+  pub alignment: Option<u32>,
+  /// Left and right margin in pixels, 40 by default
+  pub margin_h: Option<u32>,
+  /// Vertical margin in pixels, 50 by default
+  pub margin_v: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct SubtitleOptions {
+  /// Added to every timestamp in milliseconds, negative values show the subtitles earlier
+  pub offset_ms: Option<i64>,
+  /// Prefix the cues with `Segment.speaker`, alternating at the turns detected with `tdrzEnable` if the segments have none
+  pub speaker_labels: Option<bool>,
+  /// Highlight the words of the WebVTT and ASS cues as they are spoken, requires `tokenTimestamps` or `dtwTokenTimestamps`
+  pub word_timestamps: Option<bool>,
+  /// Styles of the ASS output, the speakers take turns using them with `speakerLabels`
+  pub ass_styles: Option<Vec<AssStyle>>,
+}
+
+struct Cue<'a> {
+  start: u64,
+  end: u64,
+  speaker: Option<u32>,
+  segment: &'a Segment,
+}
+
+fn cues<'a>(segments: &'a [Segment], options: &SubtitleOptions) -> Vec<Cue<'a>> {
+  let offset_ms = options.offset_ms.unwrap_or(0);
+  let speaker_labels = options.speaker_labels.unwrap_or(false);
+  let mut turn_speaker = 0;
+  segments
+    .iter()
+    .map(|segment| {
+      let cue = Cue {
+        start: shift(segment.start, offset_ms),
+        end: shift(segment.end, offset_ms),
+        speaker: speaker_labels.then_some(segment.speaker.unwrap_or(turn_speaker)),
+        segment,
+      };
+      if segment.speaker_turn_next {
+        turn_speaker = 1 - turn_speaker;
+      }
+      cue
+    })
+    .collect()
+}
+
+fn speaker_label(speaker: u32) -> String {
+  format!("Speaker {}", speaker + 1)
+}
+
+fn shift(ms: u32, offset_ms: i64) -> u64 {
+  (ms as i64 + offset_ms).max(0) as u64
+}
+
+/// `HH:MM:SS` followed by the milliseconds
+fn timestamp(ms: u64, separator: char) -> String {
+  format!(
+    "{:02}:{:02}:{:02}{separator}{:03}",
+    ms / 3_600_000,
+    ms / 60_000 % 60,
+    ms / 1000 % 60,
+    ms % 1000
+  )
+}
+
+fn srt(cues: &[Cue]) -> String {
+  let mut output = String::new();
+  for (i, cue) in cues.iter().enumerate() {
+    let _ = writeln!(
+      output,
+      "{}\n{} --> {}",
+      i + 1,
+      timestamp(cue.start, ','),
+      timestamp(cue.end, ',')
+    );
+    if let Some(speaker) = cue.speaker {
+      let _ = write!(output, "[{}] ", speaker_label(speaker));
+    }
+    let _ = writeln!(output, "{}\n", cue.segment.text);
+  }
+  output
+}
+
+/// `&`, `<` and `>` would start an escape or a tag in the cue text
+fn vtt_text(text: &str) -> String {
+  text
+    .replace('&', "&amp;")
+    .replace('<', "&lt;")
+    .replace('>', "&gt;")
+}
+
+fn web_vtt(cues: &[Cue], word_timestamps: bool, offset_ms: i64) -> String {
+  let mut output = String::from("WEBVTT\n\n");
+  for cue in cues {
+    let _ = writeln!(
+      output,
+      "{} --> {}",
+      timestamp(cue.start, '.'),
+      timestamp(cue.end, '.')
+    );
+    if let Some(speaker) = cue.speaker {
+      let _ = write!(output, "<v {}>", vtt_text(&speaker_label(speaker)));
+    }
+    let words = segment_words(cue.segment);
+    if word_timestamps && !words.is_empty() {
+      for (i, word) in words.iter().enumerate() {
+        if i > 0 {
+          output.push(' ');
+        }
+        match word.start.map(|start| shift(start, offset_ms)) {
+          // the timestamp tags must be strictly inside the cue
+          Some(start) if i > 0 && start > cue.start && start < cue.end => {
+            let _ = write!(
+              output,
+              "<{}>{}",
+              timestamp(start, '.'),
+              vtt_text(&word.text)
+            );
+          }
+          _ => output.push_str(&vtt_text(&word.text)),
+        }
+      }
+      output.push_str("\n\n");
+    } else {
+      let _ = writeln!(output, "{}\n", vtt_text(&cue.segment.text));
+    }
+  }
+  output
+}
+
+fn lrc(cues: &[Cue]) -> String {
+  let mut output = String::from("[by:whisper.cpp]\n");
+  for cue in cues {
+    let _ = write!(
+      output,
+      "[{:02}:{:02}.{:02}]",
+      cue.start / 60_000,
+      cue.start / 1000 % 60,
+      cue.start % 1000 / 10
+    );
+    if let Some(speaker) = cue.speaker {
+      let _ = write!(output, "{}: ", speaker_label(speaker));
+    }
+    let _ = writeln!(output, "{}", cue.segment.text);
+  }
+  output
+}
+
+const ASS_HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+PlayResX: 1920
+PlayResY: 1080
+WrapStyle: 0
+ScaledBorderAndShadow: yes
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+";
+
+const ASS_EVENTS: &str = "
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+/// `H:MM:SS.cc`
+fn ass_timestamp(ms: u64) -> String {
+  let cs = ms / 10;
+  format!(
+    "{}:{:02}:{:02}.{:02}",
+    cs / 360_000,
+    cs / 6000 % 60,
+    cs / 100 % 60,
+    cs % 100
+  )
+}
+
+/// `{`, `}` and `\` would start an override block or an escape, renderers disagree on escaping them
+/// so they are replaced by their fullwidth forms
+fn ass_text(text: &str) -> String {
+  text
+    .replace('\\', "\u{ff3c}")
+    .replace('{', "\u{ff5b}")
+    .replace('}', "\u{ff5d}")
+    .replace('\n', "\\N")
+}
+
+/// Text of the cue with a `\k` tag before every word, every word is highlighted until the next one starts.
+/// `None` if a word has no timestamp.
+fn karaoke(cue: &Cue, offset_ms: i64) -> Option<String> {
+  let words = segment_words(cue.segment);
+  // the karaoke durations are in centiseconds
+  let starts = words
+    .iter()
+    .map(|word| Some(shift(word.start?, offset_ms).clamp(cue.start, cue.end) / 10))
+    .collect::<Option<Vec<_>>>()?;
+  if words.is_empty() {
+    return None;
+  }
+  let mut output = String::new();
+  let mut position = cue.start / 10;
+  for (i, word) in words.iter().enumerate() {
+    let start = starts[i].max(position);
+    if i > 0 {
+      output.push(' ');
+    } else if start > position {
+      // silence before the first word
+      let _ = write!(output, "{{\\k{}}}", start - position);
+    }
+    let end = starts
+      .get(i + 1)
+      .copied()
+      .unwrap_or(cue.end / 10)
+      .max(start);
+    let _ = write!(output, "{{\\k{}}}{}", end - start, ass_text(&word.text));
+    position = end;
+  }
+  Some(output)
+}
+
+fn ass(cues: &[Cue], options: &SubtitleOptions) -> String {
+  let default_styles = [AssStyle::default()];
+  let styles = match &options.ass_styles {
+    Some(styles) if !styles.is_empty() => &styles[..],
+    _ => &default_styles[..],
+  };
+  let names = styles
+    .iter()
+    .enumerate()
+    .map(|(i, style)| match &style.name {
+      Some(name) => name.clone(),
+      None if i == 0 => "Default".to_string(),
+      None => format!("Style{}", i + 1),
+    })
+    .collect::<Vec<_>>();
+
+  let mut output = String::from(ASS_HEADER);
+  for (style, name) in styles.iter().zip(&names) {
+    let margin_h = style.margin_h.unwrap_or(40);
+    let _ = writeln!(
+      output,
+      "Style: {name},{},{},{},{},{},{},{},{},0,0,100,100,0,0,1,{},{},{},{margin_h},{margin_h},{},1",
+      style.font_name.as_deref().unwrap_or("Arial"),
+      style.font_size.unwrap_or(64),
+      style.primary_colour.as_deref().unwrap_or("&H00FFFFFF"),
+      style.secondary_colour.as_deref().unwrap_or("&H00A0A0A0"),
+      style.outline_colour.as_deref().unwrap_or("&H00000000"),
+      style.back_colour.as_deref().unwrap_or("&H80000000"),
+      // -1 is true in ASS
+      -(style.bold.unwrap_or(false) as i32),
+      -(style.italic.unwrap_or(false) as i32),
+      style.outline.unwrap_or(3.0),
+      style.shadow.unwrap_or(0.0),
+      style.alignment.unwrap_or(2),
+      style.margin_v.unwrap_or(50),
+    );
+  }
+
+  output.push_str(ASS_EVENTS);
+  let word_timestamps = options.word_timestamps.unwrap_or(false);
+  let offset_ms = options.offset_ms.unwrap_or(0);
+  for cue in cues {
+    let style = &names[cue.speaker.unwrap_or(0) as usize % names.len()];
+    let highlighted = if word_timestamps {
+      karaoke(cue, offset_ms)
+    } else {
+      None
+    };
+    let text = highlighted.unwrap_or_else(|| ass_text(&cue.segment.text));
+    let _ = writeln!(
+      output,
+      "Dialogue: 0,{},{},{style},{},0,0,0,,{text}",
+      ass_timestamp(cue.start),
+      ass_timestamp(cue.end),
+      cue.speaker.map(speaker_label).unwrap_or_default(),
+    );
+  }
+  output
+}
+
+#[napi]
+/// Serialize the segments of a transcription, e.g. `WhisperFullResult.segments`
+pub fn format_subtitles(
+  segments: Vec<Segment>,
+  format: SubtitleFormat,
+  options: Option<SubtitleOptions>,
+) -> String {
+  let options = options.unwrap_or_default();
+  let cues = cues(&segments, &options);
+  match format {
+    SubtitleFormat::Srt => srt(&cues),
+    SubtitleFormat::WebVtt => web_vtt(
+      &cues,
+      options.word_timestamps.unwrap_or(false),
+      options.offset_ms.unwrap_or(0),
+    ),
+    SubtitleFormat::Lrc => lrc(&cues),
+    SubtitleFormat::Ass => ass(&cues, &options),
+  }
+}
+
+#[napi]
+/// Write the output of `formatSubtitles` to `path`
+pub fn write_subtitles(
+  path: String,
+  segments: Vec<Segment>,
+  format: SubtitleFormat,
+  options: Option<SubtitleOptions>,
+) -> Result<()> {
+  std::fs::write(path, format_subtitles(segments, format, options))?;
+  Ok(())
+}
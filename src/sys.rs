@@ -1,7 +1,7 @@
 #![allow(dead_code)]
 #![allow(non_camel_case_types)]
 use std::{
-  ffi::{c_char, c_float, c_int, c_long, c_uint, c_void},
+  ffi::{c_char, c_float, c_int, c_uint, c_void},
   os::raw::c_longlong,
 };
 
@@ -131,9 +131,9 @@ pub struct whisper_token_data {
   pub plog: c_float,
   pub pt: c_float,
   pub ptsum: c_float,
-  pub t0: c_long,
-  pub t1: c_long,
-  pub t_dtw: c_long,
+  pub t0: i64,
+  pub t1: i64,
+  pub t_dtw: i64,
   pub vlen: c_float,
 }
 
@@ -302,6 +302,15 @@ pub type whisper_logits_filter_callback = Option<
   ),
 >;
 
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct whisper_model_loader {
+  pub context: *mut c_void,
+  pub read: Option<extern "C" fn(ctx: *mut c_void, output: *mut c_void, read_size: usize) -> usize>,
+  pub eof: Option<extern "C" fn(ctx: *mut c_void) -> bool>,
+  pub close: Option<extern "C" fn(ctx: *mut c_void)>,
+}
+
 #[link(name = "whisper", kind = "static")]
 extern "C" {
   pub fn whisper_lang_max_id() -> c_int;
@@ -321,6 +330,14 @@ extern "C" {
     buffer_size: usize,
     params: whisper_context_params,
   ) -> *mut whisper_context;
+  pub fn whisper_init_from_file_with_params(
+    path_model: *const c_char,
+    params: whisper_context_params,
+  ) -> *mut whisper_context;
+  pub fn whisper_init_with_params(
+    loader: *mut whisper_model_loader,
+    params: whisper_context_params,
+  ) -> *mut whisper_context;
   // mel length
   pub fn whisper_n_len(ctx: *mut whisper_context) -> c_int;
   pub fn whisper_n_len_from_state(state: *mut whisper_state) -> c_int;
@@ -341,18 +358,114 @@ extern "C" {
   pub fn whisper_model_n_mels(ctx: *mut whisper_context) -> c_int;
   pub fn whisper_model_ftype(ctx: *mut whisper_context) -> c_int;
   pub fn whisper_model_type(ctx: *mut whisper_context) -> c_int;
+  pub fn whisper_model_type_readable(ctx: *mut whisper_context) -> *const c_char;
+
+  pub fn whisper_pcm_to_mel(
+    ctx: *mut whisper_context,
+    samples: *const c_float,
+    n_samples: c_int,
+    n_threads: c_int,
+  ) -> c_int;
+  pub fn whisper_pcm_to_mel_with_state(
+    ctx: *mut whisper_context,
+    state: *mut whisper_state,
+    samples: *const c_float,
+    n_samples: c_int,
+    n_threads: c_int,
+  ) -> c_int;
+  pub fn whisper_lang_auto_detect(
+    ctx: *mut whisper_context,
+    offset_ms: c_int,
+    n_threads: c_int,
+    lang_probs: *mut c_float,
+  ) -> c_int;
+  pub fn whisper_lang_auto_detect_with_state(
+    ctx: *mut whisper_context,
+    state: *mut whisper_state,
+    offset_ms: c_int,
+    n_threads: c_int,
+    lang_probs: *mut c_float,
+  ) -> c_int;
 
   pub fn whisper_full_lang_id(ctx: *mut whisper_context) -> c_int;
   pub fn whisper_full_lang_id_from_state(state: *mut whisper_state) -> c_int;
-  pub fn whisper_token_count(ctx: *mut whisper_context) -> c_int;
+  pub fn whisper_tokenize(
+    ctx: *mut whisper_context,
+    text: *const c_char,
+    tokens: *mut whisper_token,
+    n_max_tokens: c_int,
+  ) -> c_int;
+  pub fn whisper_token_count(ctx: *mut whisper_context, text: *const c_char) -> c_int;
+  pub fn whisper_token_to_str(ctx: *mut whisper_context, token: whisper_token) -> *const c_char;
+  pub fn whisper_token_eot(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_sot(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_solm(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_prev(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_nosp(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_not(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_beg(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_lang(ctx: *mut whisper_context, lang_id: c_int) -> whisper_token;
+  pub fn whisper_token_translate(ctx: *mut whisper_context) -> whisper_token;
+  pub fn whisper_token_transcribe(ctx: *mut whisper_context) -> whisper_token;
   pub fn whisper_full_n_segments(ctx: *mut whisper_context) -> c_int;
   pub fn whisper_full_n_segments_from_state(state: *mut whisper_state) -> c_int;
   pub fn whisper_full_get_segment_t0(ctx: *mut whisper_context, i_segment: c_int) -> c_longlong;
+  pub fn whisper_full_get_segment_t0_from_state(
+    state: *mut whisper_state,
+    i_segment: c_int,
+  ) -> c_longlong;
   pub fn whisper_full_get_segment_t1(ctx: *mut whisper_context, i_segment: c_int) -> c_longlong;
+  pub fn whisper_full_get_segment_t1_from_state(
+    state: *mut whisper_state,
+    i_segment: c_int,
+  ) -> c_longlong;
+  pub fn whisper_full_get_segment_speaker_turn_next(
+    ctx: *mut whisper_context,
+    i_segment: c_int,
+  ) -> bool;
+  pub fn whisper_full_get_segment_speaker_turn_next_from_state(
+    state: *mut whisper_state,
+    i_segment: c_int,
+  ) -> bool;
   pub fn whisper_full_get_segment_text(
     ctx: *mut whisper_context,
     i_segment: c_int,
   ) -> *const c_char;
+  pub fn whisper_full_get_segment_text_from_state(
+    state: *mut whisper_state,
+    i_segment: c_int,
+  ) -> *const c_char;
+  pub fn whisper_full_get_segment_no_speech_prob(
+    ctx: *mut whisper_context,
+    i_segment: c_int,
+  ) -> c_float;
+  pub fn whisper_full_get_segment_no_speech_prob_from_state(
+    state: *mut whisper_state,
+    i_segment: c_int,
+  ) -> c_float;
+  pub fn whisper_full_n_tokens(ctx: *mut whisper_context, i_segment: c_int) -> c_int;
+  pub fn whisper_full_n_tokens_from_state(state: *mut whisper_state, i_segment: c_int) -> c_int;
+  pub fn whisper_full_get_token_text(
+    ctx: *mut whisper_context,
+    i_segment: c_int,
+    i_token: c_int,
+  ) -> *const c_char;
+  pub fn whisper_full_get_token_text_from_state(
+    ctx: *mut whisper_context,
+    state: *mut whisper_state,
+    i_segment: c_int,
+    i_token: c_int,
+  ) -> *const c_char;
+  pub fn whisper_full_get_token_data(
+    ctx: *mut whisper_context,
+    i_segment: c_int,
+    i_token: c_int,
+  ) -> whisper_token_data;
+  pub fn whisper_full_get_token_data_from_state(
+    state: *mut whisper_state,
+    i_segment: c_int,
+    i_token: c_int,
+  ) -> whisper_token_data;
   #[must_use]
   pub fn whisper_full(
     ctx: *mut whisper_context,
@@ -360,6 +473,13 @@ extern "C" {
     samples: *const c_float,
     n_samples: c_int,
   ) -> c_int;
+  pub fn whisper_full_parallel(
+    ctx: *mut whisper_context,
+    params: whisper_full_params,
+    samples: *const c_float,
+    n_samples: c_int,
+    n_processors: c_int,
+  ) -> c_int;
   pub fn whisper_full_with_state(
     ctx: *mut whisper_context,
     state: *mut whisper_state,
@@ -374,4 +494,48 @@ extern "C" {
   pub fn whisper_free_state(state: *mut whisper_state);
 
   pub fn whisper_log_set(log_callback: ggml_log_callback, user_data: *mut c_void);
+  pub fn whisper_print_system_info() -> *const c_char;
+}
+
+pub mod ggml_type {
+  use std::ffi::c_uint;
+
+  pub type GgmlType = c_uint;
+
+  pub const GGML_TYPE_F32: c_uint = 0;
+  pub const GGML_TYPE_F16: c_uint = 1;
+  pub const GGML_TYPE_Q4_0: c_uint = 2;
+  pub const GGML_TYPE_Q4_1: c_uint = 3;
+  pub const GGML_TYPE_Q5_0: c_uint = 6;
+  pub const GGML_TYPE_Q5_1: c_uint = 7;
+  pub const GGML_TYPE_Q8_0: c_uint = 8;
+}
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ggml_context {
+  _unused: [u8; 0],
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct ggml_init_params {
+  pub mem_size: usize,
+  pub mem_buffer: *mut c_void,
+  pub no_alloc: bool,
+}
+
+#[link(name = "ggml", kind = "static")]
+extern "C" {
+  pub fn ggml_init(params: ggml_init_params) -> *mut ggml_context;
+  pub fn ggml_free(ctx: *mut ggml_context);
+  pub fn ggml_fp16_to_fp32_row(x: *const u16, y: *mut c_float, n: i64);
+  pub fn ggml_quantize_chunk(
+    type_: ggml_type::GgmlType,
+    src: *const c_float,
+    dst: *mut c_void,
+    start: i64,
+    nrows: i64,
+    n_per_row: i64,
+    imatrix: *const c_float,
+  ) -> usize;
 }
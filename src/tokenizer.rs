@@ -0,0 +1,152 @@
+use std::ffi::{CStr, CString};
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::{sys, Whisper, WhisperContext};
+
+impl WhisperContext {
+  pub(crate) fn tokenize(&self, text: &str) -> Result<Vec<i32>> {
+    let c_text = CString::new(text)?;
+    let n_tokens = unsafe { sys::whisper_token_count(self.inner, c_text.as_ptr()) };
+    let mut tokens = vec![0; n_tokens.max(0) as usize];
+    let n_tokens = unsafe {
+      sys::whisper_tokenize(
+        self.inner,
+        c_text.as_ptr(),
+        tokens.as_mut_ptr(),
+        tokens.len() as i32,
+      )
+    };
+    if n_tokens < 0 {
+      return Err(Error::new(
+        Status::GenericFailure,
+        format!("Failed to tokenize text, {} tokens required", -n_tokens),
+      ));
+    }
+    tokens.truncate(n_tokens as usize);
+    Ok(tokens)
+  }
+
+  /// Raw bytes of the token, a multi-byte character can be split across tokens
+  pub(crate) fn token_bytes(&self, token: i32) -> Result<Vec<u8>> {
+    // whisper.cpp throws on out of range tokens
+    if token < 0 || token >= unsafe { sys::whisper_n_vocab(self.inner) } {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Token {token} is out of the vocabulary"),
+      ));
+    }
+    let text = unsafe { sys::whisper_token_to_str(self.inner, token) };
+    if text.is_null() {
+      return Ok(Vec::new());
+    }
+    Ok(unsafe { CStr::from_ptr(text) }.to_bytes().to_vec())
+  }
+}
+
+#[napi]
+impl Whisper {
+  #[napi]
+  /// Return the number of tokens in the provided text
+  pub fn count(&self, text: String) -> Result<u32> {
+    let c_text = CString::new(text)?;
+    Ok(unsafe { sys::whisper_token_count(self.ctx.inner, c_text.as_ptr()) }.max(0) as u32)
+  }
+
+  #[napi]
+  /// Convert the provided text into tokens
+  pub fn tokenize(&self, text: String) -> Result<Int32Array> {
+    self.ctx.tokenize(&text).map(Int32Array::new)
+  }
+
+  #[napi]
+  /// Return the text of the token, which can be a partial UTF-8 character
+  pub fn token_to_str(&self, token: i32) -> Result<String> {
+    Ok(String::from_utf8_lossy(&self.ctx.token_bytes(token)?).into_owned())
+  }
+
+  #[napi]
+  /// Convert the tokens back into text
+  pub fn detokenize(&self, tokens: &[i32]) -> Result<String> {
+    let mut bytes = Vec::with_capacity(tokens.len() * 4);
+    for token in tokens {
+      bytes.extend_from_slice(&self.ctx.token_bytes(*token)?);
+    }
+    Ok(String::from_utf8_lossy(&bytes).into_owned())
+  }
+
+  #[napi]
+  /// Tokenize words for `WhisperFullParams.hotWords`, e.g. product names to boost.
+  /// Every word is tokenized with and without a leading space.
+  pub fn tokenize_hot_words(&self, words: Vec<String>) -> Result<Vec<Vec<i32>>> {
+    let mut tokens = Vec::new();
+    for word in words {
+      let word = word.trim();
+      if word.is_empty() {
+        continue;
+      }
+      tokens.push(self.ctx.tokenize(word)?);
+      tokens.push(self.ctx.tokenize(&format!(" {word}"))?);
+    }
+    Ok(tokens)
+  }
+
+  #[napi(getter)]
+  /// End of transcript
+  pub fn get_token_eot(&self) -> i32 {
+    unsafe { sys::whisper_token_eot(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  /// Start of transcript
+  pub fn get_token_sot(&self) -> i32 {
+    unsafe { sys::whisper_token_sot(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  /// Start of the previous text, followed by the prompt tokens
+  pub fn get_token_prev(&self) -> i32 {
+    unsafe { sys::whisper_token_prev(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  /// Start of language model
+  pub fn get_token_solm(&self) -> i32 {
+    unsafe { sys::whisper_token_solm(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  /// No timestamps
+  pub fn get_token_not(&self) -> i32 {
+    unsafe { sys::whisper_token_not(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  /// Timestamp begin, the first timestamp token
+  pub fn get_token_beg(&self) -> i32 {
+    unsafe { sys::whisper_token_beg(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  pub fn get_token_translate(&self) -> i32 {
+    unsafe { sys::whisper_token_translate(self.ctx.inner) }
+  }
+
+  #[napi(getter)]
+  pub fn get_token_transcribe(&self) -> i32 {
+    unsafe { sys::whisper_token_transcribe(self.ctx.inner) }
+  }
+
+  #[napi]
+  /// Return the token of the specified language id, e.g. `whisper.tokenLang(Whisper.langId('de'))`
+  pub fn token_lang(&self, lang_id: i32) -> Result<i32> {
+    if lang_id < 0 || lang_id > unsafe { sys::whisper_lang_max_id() } {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!("Language id {lang_id} is out of range"),
+      ));
+    }
+    Ok(unsafe { sys::whisper_token_lang(self.ctx.inner, lang_id) })
+  }
+}
@@ -0,0 +1,237 @@
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+
+use crate::WHISPER_SAMPLE_RATE;
+
+/// Silence inserted between the speech regions, same as the VAD of whisper.cpp
+const REGION_GAP_MS: u32 = 100;
+
+/// Cutoff of the high-pass filter applied before measuring the energy, removes hum and rumble
+const HIGH_PASS_CUTOFF: f32 = 100.0;
+
+#[napi(object)]
+#[derive(Debug, Clone, Default)]
+/// Energy based voice activity detection, the noise floor is estimated from the quietest frames of the audio
+pub struct WhisperVadParams {
+  /// Frames louder than the noise floor by this many decibels are speech, 10 by default
+  pub threshold_db: Option<f64>,
+  /// Frames quieter than this level in dBFS are never speech, -50 by default
+  pub min_energy_db: Option<f64>,
+  /// Length of the analysis frames, 30 by default
+  pub frame_ms: Option<u32>,
+  /// Shorter speech regions are dropped, 250 by default
+  pub min_speech_ms: Option<u32>,
+  /// Shorter pauses do not split a speech region, 500 by default
+  pub min_silence_ms: Option<u32>,
+  /// Audio kept before and after every speech region, 200 by default
+  pub speech_pad_ms: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct VadParams {
+  threshold_db: f32,
+  min_energy_db: f32,
+  frame_ms: u32,
+  min_speech_ms: u32,
+  min_silence_ms: u32,
+  speech_pad_ms: u32,
+}
+
+impl VadParams {
+  pub(crate) fn new(params: Option<WhisperVadParams>) -> Result<Self> {
+    let params = params.unwrap_or_default();
+    let frame_ms = params.frame_ms.unwrap_or(30);
+    if frame_ms == 0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "frameMs must be greater than 0",
+      ));
+    }
+    Ok(Self {
+      threshold_db: params.threshold_db.unwrap_or(10.0) as f32,
+      min_energy_db: params.min_energy_db.unwrap_or(-50.0) as f32,
+      frame_ms,
+      min_speech_ms: params.min_speech_ms.unwrap_or(250),
+      min_silence_ms: params.min_silence_ms.unwrap_or(500),
+      speech_pad_ms: params.speech_pad_ms.unwrap_or(200),
+    })
+  }
+}
+
+impl From<VadParams> for WhisperVadParams {
+  fn from(params: VadParams) -> Self {
+    Self {
+      threshold_db: Some(params.threshold_db as f64),
+      min_energy_db: Some(params.min_energy_db as f64),
+      frame_ms: Some(params.frame_ms),
+      min_speech_ms: Some(params.min_speech_ms),
+      min_silence_ms: Some(params.min_silence_ms),
+      speech_pad_ms: Some(params.speech_pad_ms),
+    }
+  }
+}
+
+#[napi(object)]
+#[derive(Debug, Clone, Copy)]
+pub struct SpeechRegion {
+  /// Start time in milliseconds
+  pub start: u32,
+  /// End time in milliseconds
+  pub end: u32,
+}
+
+fn ms_samples(ms: u32) -> usize {
+  (ms as u64 * WHISPER_SAMPLE_RATE as u64 / 1000) as usize
+}
+
+fn samples_ms(n_samples: usize) -> u32 {
+  (n_samples as u64 * 1000 / WHISPER_SAMPLE_RATE as u64) as u32
+}
+
+/// Energy of every frame in dBFS after a first order high-pass filter
+fn frame_energies(samples: &[f32], frame_len: usize) -> Vec<f32> {
+  let rc = 1.0 / (2.0 * std::f32::consts::PI * HIGH_PASS_CUTOFF);
+  let dt = 1.0 / WHISPER_SAMPLE_RATE as f32;
+  let alpha = rc / (rc + dt);
+  let mut prev_input = samples.first().copied().unwrap_or_default();
+  let mut prev_output = 0.0f32;
+  samples
+    .chunks(frame_len)
+    .map(|frame| {
+      let mut energy = 0.0f64;
+      for sample in frame {
+        prev_output = alpha * (prev_output + sample - prev_input);
+        prev_input = *sample;
+        energy += (prev_output * prev_output) as f64;
+      }
+      (10.0 * (energy / frame.len() as f64 + 1e-10).log10()) as f32
+    })
+    .collect()
+}
+
+/// Speech regions as sample ranges, padded and merged
+pub(crate) fn detect(samples: &[f32], params: &VadParams) -> Vec<(usize, usize)> {
+  let frame_len = ms_samples(params.frame_ms).max(1);
+  let energies = frame_energies(samples, frame_len);
+  if energies.is_empty() {
+    return Vec::new();
+  }
+
+  // the 10th percentile is the noise floor, recordings are rarely speech 90% of the time
+  let mut sorted = energies.clone();
+  sorted.sort_by(f32::total_cmp);
+  let noise_floor = sorted[sorted.len() / 10];
+  let threshold = (noise_floor + params.threshold_db).max(params.min_energy_db);
+
+  let min_silence_frames = (params.min_silence_ms / params.frame_ms).max(1) as usize;
+  let mut frames = Vec::new();
+  let mut current: Option<(usize, usize)> = None;
+  for (i, energy) in energies.iter().enumerate() {
+    if *energy > threshold {
+      current = Some(match current {
+        Some((start, _)) => (start, i + 1),
+        None => (i, i + 1),
+      });
+    } else if let Some((start, end)) = current {
+      if i + 1 - end >= min_silence_frames {
+        frames.push((start, end));
+        current = None;
+      }
+    }
+  }
+  frames.extend(current);
+
+  let pad = ms_samples(params.speech_pad_ms);
+  let min_speech = ms_samples(params.min_speech_ms);
+  let mut regions: Vec<(usize, usize)> = Vec::new();
+  for (start, end) in frames {
+    let (start, end) = (start * frame_len, (end * frame_len).min(samples.len()));
+    if end - start < min_speech {
+      continue;
+    }
+    let (start, end) = (start.saturating_sub(pad), (end + pad).min(samples.len()));
+    match regions.last_mut() {
+      Some(last) if start <= last.1 => last.1 = end,
+      _ => regions.push((start, end)),
+    }
+  }
+  regions
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TimelineRegion {
+  /// Start in the audio passed to whisper.cpp
+  decoded: u32,
+  /// Start in the original audio
+  original: u32,
+  len: u32,
+}
+
+#[derive(Debug, Default)]
+/// Maps the timestamps of the audio passed to whisper.cpp back to the audio passed by the user
+pub(crate) struct Timeline {
+  /// Start of the window decoded by `WhisperStream`
+  pub(crate) offset_ms: u32,
+  /// Speech regions concatenated by the VAD, empty when the audio is decoded as is
+  regions: Vec<TimelineRegion>,
+}
+
+impl Timeline {
+  pub(crate) fn clear_regions(&mut self) {
+    self.regions.clear();
+  }
+
+  pub(crate) fn map(&self, ms: u32) -> u32 {
+    let i = self.regions.partition_point(|region| region.decoded <= ms);
+    let ms = match i.checked_sub(1).map(|i| self.regions[i]) {
+      // timestamps in the gap after a region are moved to its end
+      Some(region) => region.original + (ms - region.decoded).min(region.len),
+      None => self.regions.first().map_or(ms, |region| region.original),
+    };
+    self.offset_ms + ms
+  }
+
+  /// Concatenate the speech regions of `samples` separated by a short silence and remember where they came from
+  pub(crate) fn speech_only(&mut self, samples: &[f32], params: &VadParams) -> Vec<f32> {
+    self.regions.clear();
+    let regions = detect(samples, params);
+    let gap = ms_samples(REGION_GAP_MS);
+    let mut output = Vec::with_capacity(
+      regions
+        .iter()
+        .map(|(start, end)| end - start + gap)
+        .sum::<usize>(),
+    );
+    for (start, end) in regions {
+      if !output.is_empty() {
+        output.resize(output.len() + gap, 0.0);
+      }
+      self.regions.push(TimelineRegion {
+        decoded: samples_ms(output.len()),
+        original: samples_ms(start),
+        len: samples_ms(end - start),
+      });
+      output.extend_from_slice(&samples[start..end]);
+    }
+    output
+  }
+}
+
+#[napi]
+/// Speech regions of 16 kHz mono samples, e.g. the output of `decodeAudio`.
+/// Set `WhisperFullParams.vad` to transcribe only the speech regions.
+pub fn detect_speech(
+  samples: &[f32],
+  params: Option<WhisperVadParams>,
+) -> Result<Vec<SpeechRegion>> {
+  let params = VadParams::new(params)?;
+  Ok(
+    detect(samples, &params)
+      .into_iter()
+      .map(|(start, end)| SpeechRegion {
+        start: samples_ms(start),
+        end: samples_ms(end),
+      })
+      .collect(),
+  )
+}
@@ -0,0 +1,87 @@
+use napi_derive::napi;
+
+use crate::{full_params::Segment, result::Token};
+
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct Word {
+  /// Text of the word without the leading space, punctuation stays attached
+  pub text: String,
+  /// Start time in milliseconds, requires `tokenTimestamps` or `dtwTokenTimestamps` on the context
+  pub start: Option<u32>,
+  /// End time in milliseconds, requires `tokenTimestamps` or `dtwTokenTimestamps` on the context
+  pub end: Option<u32>,
+  /// Mean probability of the tokens of the word
+  pub probability: f64,
+  /// Lowest probability of the tokens of the word
+  pub min_probability: f64,
+  /// Index of the segment the word belongs to
+  pub segment: u32,
+}
+
+/// Text tokens of the segment grouped by word, a token starting with a space starts a new word.
+/// The rest of a multi-byte character never starts with a space, so it stays with the start of it.
+fn word_tokens(segment: &Segment) -> Vec<Vec<&Token>> {
+  let mut words: Vec<Vec<&Token>> = Vec::new();
+  for token in segment.tokens.iter().filter(|token| !token.special) {
+    match words.last_mut() {
+      Some(word) if !token.bytes.starts_with(b" ") => word.push(token),
+      _ => words.push(vec![token]),
+    }
+  }
+  words
+}
+
+/// Words of the segment. With DTW every word lasts until the next one starts,
+/// otherwise it spans the timestamps of its first and last token.
+pub(crate) fn segment_words(segment: &Segment) -> Vec<Word> {
+  let words = word_tokens(segment);
+  let dtw = words
+    .iter()
+    .flatten()
+    .all(|token| token.dtw_timestamp.is_some());
+  let starts = words
+    .iter()
+    .map(|tokens| {
+      if dtw {
+        tokens[0].dtw_timestamp
+      } else {
+        tokens[0].start
+      }
+    })
+    .collect::<Vec<_>>();
+  words
+    .iter()
+    .enumerate()
+    .map(|(i, tokens)| {
+      let start = starts[i];
+      let end = if dtw {
+        Some(starts.get(i + 1).copied().flatten().unwrap_or(segment.end))
+      } else {
+        tokens[tokens.len() - 1].end
+      };
+      let bytes = tokens
+        .iter()
+        .flat_map(|token| token.bytes.iter().copied())
+        .collect::<Vec<_>>();
+      Word {
+        text: String::from_utf8_lossy(&bytes).trim_start().to_string(),
+        start,
+        end: end.map(|end| end.max(start.unwrap_or(end))),
+        probability: tokens.iter().map(|token| token.p).sum::<f64>() / tokens.len() as f64,
+        min_probability: tokens
+          .iter()
+          .map(|token| token.p)
+          .fold(f64::INFINITY, f64::min),
+        segment: segment.index,
+      }
+    })
+    .collect()
+}
+
+#[napi]
+/// Merge the tokens of the segments into words for click-to-seek transcripts, e.g. of `WhisperFullResult.segments`.
+/// The timestamps come from DTW when `dtwTokenTimestamps` is enabled on the context, from `tokenTimestamps` otherwise.
+pub fn split_words(segments: Vec<Segment>) -> Vec<Word> {
+  segments.iter().flat_map(segment_words).collect()
+}